@@ -0,0 +1,9 @@
+//! Env file line parser and secret values shared by [zenv](https://docs.rs/zenv) and its
+//! procedural macros. Use them through the `zenv` crate, instead of depending on this crate
+//! directly.
+
+mod line;
+mod secret;
+
+pub use line::*;
+pub use secret::*;
@@ -1,13 +1,20 @@
+use std::fmt;
+
+use crate::secret::Secrets;
+
 const LF: char = '\n';
-const HASH: char = '#';
+const CR: char = '\r';
+const BOM: char = '\u{feff}';
+pub(crate) const HASH: char = '#';
 const B_SLASH: char = '\\';
 const S_QUOTE: char = '\'';
 const D_QUOTE: char = '"';
 const EXPORT: &str = "export";
 const EXPORT_WS: &str = "export ";
+const INCLUDE: [&str; 3] = ["#include ", "source ", ". "];
 
 /// Type of the quote
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Quote {
     /// When the value is single quoted i.e. `'`
     Single,
@@ -20,7 +27,9 @@ pub enum Quote {
 }
 
 /// To collect the info about the current line
-#[derive(Debug, PartialEq)]
+///
+/// The [`Debug`](fmt::Debug) output masks the value if the key looks like a secret
+#[derive(PartialEq)]
 pub struct KeyVal<'k> {
     /// `key` of the variable
     pub k: &'k str,
@@ -32,6 +41,18 @@ pub struct KeyVal<'k> {
     pub q: Quote,
 }
 
+impl<'k> fmt::Debug for KeyVal<'k> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = Secrets::default().mask(self.k, &self.v);
+
+        f.debug_struct("KeyVal")
+            .field("k", &self.k)
+            .field("v", &v)
+            .field("q", &self.q)
+            .finish()
+    }
+}
+
 /// (Can be) Used to parse the current line
 ///
 /// Example
@@ -55,12 +76,25 @@ pub struct KeyVal<'k> {
 /// let k = "S_QUOTED";
 /// let v = "single_quoted".to_string();
 /// assert_eq!(quoted, Line::KeyVal(KeyVal { k, v, q: Quote::Single }));
+///
+/// // Including other file
+/// let include = Line::from("#include ../.env.common");
+///
+/// assert_eq!(include, Line::Include("../.env.common"));
+/// assert_eq!(Line::from("source .env.common"), Line::Include(".env.common"));
 /// ```
+///
+/// More kinds of lines may be added later, so a `match` needs a wildcard arm
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Line<'l> {
     /// When the current line is a `key=val` pair
     KeyVal(KeyVal<'l>),
 
+    /// When the current line includes another file using `#include <path>`, `source <path>`
+    /// or `. <path>`. `Zenv` only follows it if the includes are enabled
+    Include(&'l str),
+
     /// When the current line is empty
     Empty,
 }
@@ -106,6 +140,35 @@ impl<'l> Line<'l> {
         }
     }
 
+    /// Key of the text before the `=`, along with the number of bytes before it
+    fn key(k: &str) -> (usize, &str) {
+        let key = k.strip_prefix(EXPORT_WS).unwrap_or(k).trim_start();
+
+        (k.len() - key.len(), key.trim_end())
+    }
+
+    /// Column, starting from 1, where the key of the `key=val` line starts
+    #[doc(hidden)]
+    pub fn column(line: &str) -> usize {
+        let line = line.strip_prefix(BOM).unwrap_or(line);
+        let k = line.split('=').next().unwrap_or(line);
+
+        line[..Self::key(k).0].chars().count() + 1
+    }
+
+    #[doc(hidden)]
+    pub fn include(line: &str) -> Option<&str> {
+        let line = line.trim();
+        let path = INCLUDE.iter().find_map(|x| line.strip_prefix(x))?.trim();
+
+        // Quotes around the path are optional
+        let unquoted = [D_QUOTE, S_QUOTE]
+            .iter()
+            .find_map(|q| path.strip_prefix(*q)?.strip_suffix(*q));
+
+        Some(unquoted.unwrap_or(path)).filter(|x| !x.is_empty())
+    }
+
     fn retain_quote(orgnl: &str, after: String, q: Quote) -> (String, Quote) {
         // If both strings length matches then it is not closed
         if orgnl.len().eq(&(after.len() + 1)) {
@@ -120,6 +183,14 @@ impl<'l> Line<'l> {
 
 impl<'l> From<&'l str> for Line<'l> {
     fn from(line: &'l str) -> Self {
+        // Files from Windows editors can start with a BOM and end their lines with CRLF
+        let line = line.strip_prefix(BOM).unwrap_or(line);
+        let line = line.strip_suffix(CR).unwrap_or(line);
+
+        if let Some(path) = Self::include(line) {
+            return Self::Include(path);
+        }
+
         if line.is_empty() || line.starts_with(HASH) || line.trim().eq(EXPORT) {
             return Self::Empty;
         };
@@ -128,7 +199,7 @@ impl<'l> From<&'l str> for Line<'l> {
 
         match (parts.next(), parts.next()) {
             (Some(k), Some(v)) => {
-                let key = k.strip_prefix(EXPORT_WS).unwrap_or(k).trim();
+                let (_, key) = Self::key(k);
                 let mut chars = v.chars();
 
                 let first = chars.next();
@@ -0,0 +1,196 @@
+use std::fmt;
+
+/// Placeholder which is printed instead of the actual secret
+pub const MASK: &str = "***";
+
+/// Key patterns which are treated as secrets by default
+pub const DEFAULT_PATTERNS: [&str; 3] = ["*_SECRET", "*_TOKEN", "*PASSWORD*"];
+
+/// Value which never shows up in the [`Debug`] or [`Display`](fmt::Display) output
+///
+/// Example
+/// ```
+/// use zenv::Secret;
+///
+/// let secret = Secret::new("hunter2".to_string());
+///
+/// assert_eq!(format!("{}", secret), "***");
+/// assert_eq!(format!("{:?}", secret), "***");
+/// assert_eq!(secret.expose(), "hunter2");
+/// ```
+#[derive(Clone, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wrap the value as a secret
+    pub fn new(val: String) -> Self {
+        Self(val)
+    }
+
+    /// Get the real value of the secret
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Consume the secret and return the real value
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+/// Value of a variable which is either a plain text or a [`Secret`]
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    /// When the key is not considered as a secret
+    Plain(String),
+
+    /// When the key matches one of the secret patterns or is annotated with `# @secret`
+    Secret(Secret),
+}
+
+impl Value {
+    /// Get the real value regardless of it being a secret or not
+    pub fn expose(&self) -> &str {
+        match self {
+            Self::Plain(x) => x,
+            Self::Secret(x) => x.expose(),
+        }
+    }
+
+    /// Whether the value is a secret or not
+    pub fn is_secret(&self) -> bool {
+        matches!(self, Self::Secret(_))
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain(x) => fmt::Debug::fmt(x, f),
+            Self::Secret(x) => fmt::Debug::fmt(x, f),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain(x) => f.write_str(x),
+            Self::Secret(x) => fmt::Display::fmt(x, f),
+        }
+    }
+}
+
+/// Set of key patterns which decides whether a variable is a secret or not
+///
+/// Patterns only understand `*` as a wildcard, which matches any sequence of characters.
+///
+/// Example
+/// ```
+/// use zenv::Secrets;
+///
+/// let secrets = Secrets::default();
+///
+/// assert!(secrets.is_secret("GITHUB_TOKEN"));
+/// assert!(secrets.is_secret("DB_PASSWORD_OLD"));
+/// assert!(!secrets.is_secret("PORT"));
+///
+/// let secrets = Secrets::new(&["API_*"]);
+///
+/// assert!(secrets.is_secret("API_KEY"));
+/// assert!(!secrets.is_secret("GITHUB_TOKEN"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Secrets {
+    patterns: Vec<String>,
+}
+
+impl Default for Secrets {
+    fn default() -> Self {
+        Self::new(&DEFAULT_PATTERNS)
+    }
+}
+
+impl Secrets {
+    /// Create a new set from the provided patterns
+    pub fn new(patterns: &[&str]) -> Self {
+        Self {
+            patterns: patterns.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    /// Whether the key matches any of the patterns
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.patterns.iter().any(|p| glob(p, key))
+    }
+
+    /// Wrap the value into a [`Value`] depending upon the key
+    pub fn wrap(&self, key: &str, val: String) -> Value {
+        if self.is_secret(key) {
+            Value::Secret(Secret::new(val))
+        } else {
+            Value::Plain(val)
+        }
+    }
+
+    /// Returns the value or the [`MASK`] if the key is a secret. Handy for printing
+    pub fn mask<'a>(&self, key: &str, val: &'a str) -> &'a str {
+        if self.is_secret(key) {
+            MASK
+        } else {
+            val
+        }
+    }
+}
+
+/// Comment which marks the following key as a secret
+const MARKER: &str = "@secret";
+
+/// Whether the line is the `# @secret` comment, which only applies to the very next line
+#[doc(hidden)]
+pub fn marker(line: &str) -> bool {
+    line.trim().strip_prefix(crate::HASH).map(str::trim) == Some(MARKER)
+}
+
+/// Matches the text against a pattern where `*` matches any sequence of characters
+#[doc(hidden)]
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    // There is always a first part, even if the pattern is empty
+    let first = parts.next().unwrap_or_default();
+
+    let mut rest = match text.strip_prefix(first) {
+        Some(x) => x,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+
+    // No wildcard at all, so it needs to be an exact match
+    let (last, middle) = match parts.split_last() {
+        Some(x) => x,
+        None => return rest.is_empty(),
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,199 @@
+//! Procedural macros for [zenv](https://docs.rs/zenv). Use them through the `zenv` crate with the
+//! `macros` feature enabled, instead of depending on this crate directly.
+
+use proc_macro::TokenStream;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+// Same parser as `zenv`, so that the embedded variables are identical to the ones parsed at runtime
+use zenv_core::{marker, Line, Quote};
+
+// Path to the env file, along with whether to follow the includes i.e. `".env", includes`
+fn args(input: TokenStream) -> Result<(String, bool), String> {
+    let input = input.to_string();
+    let input = input.trim();
+
+    let (literal, rest) = input
+        .rfind('"')
+        .map_or((input, ""), |i| input.split_at(i + 1));
+
+    let includes = match rest.trim() {
+        "" => false,
+        x if x.strip_prefix(',').map(str::trim) == Some("includes") => true,
+        x => {
+            return Err(format!(
+                "expected `, includes` after the path, found `{}`",
+                x
+            ))
+        }
+    };
+
+    let path = literal
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .map(|x| x.replace("\\\\", "\\").replace("\\\"", "\""))
+        .ok_or_else(|| {
+            "expected a path to the env file e.g. zenv::include!(\".env\")".to_string()
+        })?;
+
+    Ok((path, includes))
+}
+
+// Returns an error if the line is not going to be parsed as it is written
+fn check(raw: &str, line: &Line) -> Result<(), String> {
+    let trimmed = raw.trim();
+
+    match line {
+        Line::KeyVal(kv) => {
+            if kv.k.is_empty() || kv.k.contains(char::is_whitespace) {
+                return Err(format!("invalid key `{}`", kv.k));
+            }
+
+            let val = raw.split_once('=').map_or("", |x| x.1).trim_start();
+
+            if kv.q == Quote::No && (val.starts_with('"') || val.starts_with('\'')) {
+                return Err(format!("unclosed quote in the value of `{}`", kv.k));
+            }
+        }
+        Line::Empty if !trimmed.is_empty() && !trimmed.starts_with('#') && trimmed != "export" => {
+            return Err(format!("expected KEY=VALUE, found `{}`", trimmed));
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Variables in the order they are defined, along with the keys marked with `# @secret`
+#[derive(Default)]
+struct Vars {
+    vars: Vec<(String, String)>,
+    marked: Vec<String>,
+}
+
+/// Reads the file, and the files included by it, into `vars`. Like `Lines::ordered`, a variable
+/// keeps the position of its first definition and the value of its last one.
+fn read(
+    path: &Path,
+    includes: bool,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    vars: &mut Vars,
+) -> Result<(), String> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| format!("Unable to find file - {} - {}", path.display(), e))?;
+
+    if stack.contains(&canonical) {
+        return Err(format!("Include cycle detected - {}", path.display()));
+    }
+
+    let text = fs::read_to_string(&canonical)
+        .map_err(|e| format!("Unable to read file - {} - {}", path.display(), e))?;
+
+    stack.push(canonical.clone());
+    files.push(canonical);
+
+    let mut is_secret = false;
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = Line::from(raw);
+
+        check(raw, &line).map_err(|e| format!("{}:{} - {}", path.display(), i + 1, e))?;
+
+        match line {
+            Line::KeyVal(kv) => {
+                if std::mem::take(&mut is_secret) && !vars.marked.iter().any(|k| k == kv.k) {
+                    vars.marked.push(kv.k.to_string());
+                }
+
+                match vars.vars.iter_mut().find(|(k, _)| k == kv.k) {
+                    Some((_, v)) => *v = kv.v,
+                    None => vars.vars.push((kv.k.to_string(), kv.v)),
+                }
+            }
+            Line::Include(include) if includes => {
+                is_secret = false;
+                let include = path.parent().unwrap_or_else(|| Path::new("")).join(include);
+                read(&include, includes, stack, files, vars)?;
+            }
+            // Annotation only applies to the very next line
+            _ => is_secret = marker(raw),
+        }
+    }
+
+    stack.pop();
+
+    Ok(())
+}
+
+fn expand(input: TokenStream) -> Result<String, String> {
+    let (path, includes) = args(input)?;
+    let root = env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+
+    let mut files = Vec::new();
+    let mut vars = Vars::default();
+
+    read(
+        &root.join(&path),
+        includes,
+        &mut Vec::new(),
+        &mut files,
+        &mut vars,
+    )?;
+
+    let mut code = String::from("{");
+
+    // Recompiles the crate whenever any of the files changes
+    for file in files {
+        code.push_str(&format!(
+            "const _: &[u8] = include_bytes!({:?});",
+            file.display().to_string()
+        ));
+    }
+
+    code.push_str("::zenv::Embedded::new(&[");
+
+    for (k, v) in vars.vars {
+        code.push_str(&format!("({:?}, {:?}),", k, v));
+    }
+
+    code.push_str("],&[");
+
+    for k in vars.marked {
+        code.push_str(&format!("{:?},", k));
+    }
+
+    code.push_str("])}");
+
+    Ok(code)
+}
+
+/// Reads and parses the env file at compile time and embeds the variables into the binary as
+/// `zenv::Embedded`. The path is relative to the root of the crate i.e. `CARGO_MANIFEST_DIR`.
+///
+/// Files included by the env file are also embedded if `includes` follows the path, same as
+/// `Zenv::includes`. Malformed lines fail the compilation with the file and the line number.
+/// Variables are not expanded.
+///
+/// Example
+/// ```ignore
+/// static ENV: zenv::Embedded = zenv::include!(".env");
+/// static ALL: zenv::Embedded = zenv::include!(".env", includes);
+///
+/// fn main() {
+///     let port = ENV.get("PORT").unwrap_or("5000");
+/// }
+/// ```
+#[proc_macro]
+pub fn include(input: TokenStream) -> TokenStream {
+    let code = match expand(input) {
+        Ok(code) => code,
+        Err(e) => format!("compile_error!({:?})", e),
+    };
+
+    code.parse().unwrap()
+}
@@ -0,0 +1,74 @@
+//! Helpers for the build scripts, to make the variables available at compile time using [`env!`]
+//!
+//! Example
+//! ```no_run
+//! // build.rs
+//! zenv::build::emit(".env").unwrap();
+//!
+//! // src/main.rs
+//! // const API_URL: &str = env!("API_URL");
+//! ```
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::Zenv;
+
+/// Returns the cargo directives for the variables, a `cargo:rustc-env=KEY=VALUE` for each one of
+/// them, followed by a `cargo:rerun-if-changed=path` for each file that was read, including the
+/// files which the values are [read from](Zenv::files). Cargo can't carry a value with a newline,
+/// so that is an error.
+///
+/// Example
+/// ```
+/// let zenv = zenv::Zenv::new("tests/.env.basic", false);
+/// let directives = zenv::build::directives(&zenv).unwrap();
+///
+/// assert_eq!(directives[0], "cargo:rustc-env=BASIC=basic");
+/// assert_eq!(directives.last().unwrap(), "cargo:rerun-if-changed=tests/.env.basic");
+/// ```
+pub fn directives(zenv: &Zenv) -> Result<Vec<String>> {
+    let mut directives = Vec::new();
+    let (vars, paths) = zenv.parse_ordered_paths()?;
+
+    for (key, val) in vars {
+        if val.contains('\n') || val.contains('\r') {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Value of `{}` contains a newline, which cargo doesn't support",
+                    key
+                ),
+            ));
+        }
+
+        directives.push(format!("cargo:rustc-env={}={}", key, val));
+    }
+
+    for path in paths {
+        directives.push(format!("cargo:rerun-if-changed={}", path.display()));
+    }
+
+    Ok(directives)
+}
+
+/// Parse the env file and print the [`directives`] for cargo. Use [`emit_with`] to configure the
+/// parsing
+pub fn emit(path: &str) -> Result<()> {
+    emit_with(&Zenv::new(path, false))
+}
+
+/// Same as [`emit`] but with a configured [`Zenv`]
+///
+/// Example
+/// ```no_run
+/// let zenv = zenv::Zenv::new(".env", true).files(true);
+///
+/// zenv::build::emit_with(&zenv).unwrap();
+/// ```
+pub fn emit_with(zenv: &Zenv) -> Result<()> {
+    for directive in directives(zenv)? {
+        println!("{}", directive);
+    }
+
+    Ok(())
+}
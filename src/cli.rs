@@ -1,15 +1,83 @@
-use crate::info::{DESC, NAME, VERSION};
+use crate::{
+    convert::Format,
+    info::{DESC, NAME, VERSION},
+};
 use lexopt::{
     Arg::{Long, Short, Value},
-    Parser,
+    Parser, ValueExt,
 };
-use std::{ffi::OsString, process};
+use std::{env, ffi::OsString, process};
+use zenv::Dialect;
+
+pub enum Subcommand {
+    // Print where the variable is defined and how its value came to be
+    Explain(String),
+    // Print the variables whose values differ between the dialects
+    CompatCheck,
+    // Print the variables in another format i.e. `--to docker-env`
+    Convert(Option<Format>),
+}
+
+impl Subcommand {
+    fn from(name: &OsString) -> Option<Self> {
+        match name.to_str()? {
+            "explain" => Some(Self::Explain(String::new())),
+            "compat-check" => Some(Self::CompatCheck),
+            "convert" => Some(Self::Convert(None)),
+            _ => None,
+        }
+    }
+}
 
 pub struct Cli {
+    // Subcommand, if any, instead of running a command
+    pub subcommand: Option<Subcommand>,
     // Whether to substitute variables or not
     pub expand: bool,
-    // Path to .env file
-    pub path: String,
+    // Whether to substitute commands or not
+    pub commands: bool,
+    // Timeout (in seconds) for the substituted commands
+    pub timeout: Option<u64>,
+    // Whether to read values from files or not
+    pub files: bool,
+    // Whether to read `KEY` from the file of `KEY_FILE` or not
+    pub file_keys: bool,
+    // Whether to include other env files or not
+    pub includes: bool,
+    // Whether to decode the env files with a UTF-16 BOM as UTF-16
+    pub utf16: bool,
+    // Syntax of the env files
+    pub dialect: Dialect,
+    // Whether to start the command with only the variables from the env file
+    pub clear: bool,
+    // Variables to inherit from the environment when the environment is cleared
+    pub keep: Vec<String>,
+    // Whether to replace the zenv process with the command instead of spawning it
+    pub exec: bool,
+    // Whether to restart the command when the env file changes
+    pub watch: bool,
+    // Seconds to wait for the command to exit before killing it
+    pub grace: Option<u64>,
+    // Whether to print what would be executed instead of executing it
+    pub dry_run: bool,
+    // Whether to log what zenv is doing
+    pub verbose: bool,
+    // Only load the variables with this prefix
+    pub prefix: Option<String>,
+    // Whether to remove the prefix from the keys
+    pub strip_prefix: bool,
+    // Only load these variables
+    pub only: Vec<String>,
+    // Key patterns of the variables which are not loaded
+    pub exclude: Vec<String>,
+    // Additional key patterns which are considered as secrets
+    pub secrets: Vec<String>,
+    // Variables which override the ones from the env file i.e. `-e KEY=VALUE`
+    pub env: Vec<(String, String)>,
+    // Env data which overrides the env file i.e. `--env-string 'A=1\nB=2'`
+    pub env_string: Option<String>,
+    // Path to .env file, `-` to read from stdin
+    pub path: OsString,
     // Name of the command
     pub command: Option<OsString>,
     // Arguments of the command
@@ -19,22 +87,79 @@ pub struct Cli {
 impl Default for Cli {
     fn default() -> Self {
         Self {
+            subcommand: None,
             expand: false,
-            path: ".env".to_string(),
+            commands: false,
+            timeout: None,
+            files: false,
+            file_keys: false,
+            includes: false,
+            utf16: false,
+            dialect: Dialect::default(),
+            clear: false,
+            keep: vec![],
+            exec: false,
+            watch: false,
+            grace: None,
+            dry_run: false,
+            verbose: false,
+            prefix: None,
+            strip_prefix: false,
+            only: vec![],
+            exclude: vec![],
+            secrets: vec![],
+            env: vec![],
+            env_string: None,
+            path: ".env".into(),
             command: None,
             args: vec![],
         }
     }
 }
 
+// Comma separated list of keys e.g. `PATH, HOME`
+fn list(keys: &str) -> impl Iterator<Item = String> + '_ {
+    keys.split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+}
+
 impl Cli {
     pub fn parse() -> Result<Self, lexopt::Error> {
-        let mut cli = Self::default();
+        let args: Vec<OsString> = env::args_os().skip(1).collect();
+
+        // Subcommands are only recognized as the very first argument, and only if there is no `--`
+        // and the rest of the arguments fit it. Otherwise it is the command to run, so that a
+        // program with the same name still works e.g. `zenv convert a.png b.jpg`
+        let subcommand = args
+            .first()
+            .and_then(Subcommand::from)
+            .filter(|_| !args.iter().any(|x| x == "--"));
+
+        if subcommand.is_some() {
+            if let Some(cli) = Self::parse_args(args[1..].to_vec(), subcommand)? {
+                return Ok(cli);
+            }
+        }
+
+        Self::parse_args(args, None).map(Option::unwrap_or_default)
+    }
 
-        let mut parser = Parser::from_env();
+    /// Returns `None` if an argument doesn't fit the `subcommand`
+    fn parse_args(
+        args: Vec<OsString>,
+        subcommand: Option<Subcommand>,
+    ) -> Result<Option<Self>, lexopt::Error> {
+        let mut cli = Self {
+            subcommand,
+            ..Self::default()
+        };
+
+        let mut parser = Parser::from_args(args);
         while let Some(arg) = parser.next()? {
             match arg {
-                Short('v') | Long("version") => {
+                Short('V') | Long("version") => {
                     println!("{} {}", NAME, VERSION);
                     process::exit(0);
                 }
@@ -43,21 +168,81 @@ impl Cli {
                     process::exit(0);
                 }
                 Short('x') | Long("expand") => cli.expand = true,
-                Short('f') | Long("file") => {
-                    cli.path = parser.value()?.into_string()?;
+                Short('c') | Long("commands") => cli.commands = true,
+                Long("files") => cli.files = true,
+                Long("file-keys") => cli.file_keys = true,
+                Long("includes") => cli.includes = true,
+                Long("utf16") => cli.utf16 = true,
+                Long("clear") | Long("isolated") => cli.clear = true,
+                Short('E') | Long("exec") => cli.exec = true,
+                Long("spawn") => cli.exec = false,
+                Short('w') | Long("watch") => cli.watch = true,
+                Short('n') | Long("dry-run") => cli.dry_run = true,
+                Short('v') | Long("verbose") => cli.verbose = true,
+                Short('s') | Long("secret") => {
+                    cli.secrets.push(parser.value()?.into_string()?);
                 }
-                Value(val) => {
-                    if cli.command == None {
-                        cli.command = Some(val);
-                    } else {
-                        cli.args.push(val);
+                Short('e') | Long("env") => {
+                    let var = parser.value()?.into_string()?;
+                    let (key, val) = var
+                        .split_once('=')
+                        .filter(|(k, _)| !k.is_empty())
+                        .ok_or_else(|| format!("Invalid variable `{}`, expected KEY=VALUE", var))?;
+                    cli.env.push((key.to_string(), val.to_string()));
+                }
+                Long("env-string") => {
+                    // Literal `\n` is also a line break, as newlines are awkward to type in shells
+                    let text = parser.value()?.into_string()?.replace("\\n", "\n");
+                    match cli.env_string.as_mut() {
+                        Some(x) => {
+                            x.push('\n');
+                            x.push_str(&text);
+                        }
+                        None => cli.env_string = Some(text),
                     }
                 }
+                Short('g') | Long("grace") => {
+                    cli.grace = Some(parser.value()?.parse()?);
+                }
+                Short('k') | Long("keep") => {
+                    cli.keep.extend(list(&parser.value()?.into_string()?));
+                }
+                Long("prefix") => {
+                    cli.prefix = Some(parser.value()?.into_string()?);
+                }
+                Long("strip-prefix") => cli.strip_prefix = true,
+                Long("only") => {
+                    cli.only.extend(list(&parser.value()?.into_string()?));
+                }
+                Long("exclude") => {
+                    cli.exclude.push(parser.value()?.into_string()?);
+                }
+                Short('d') | Long("dialect") => {
+                    cli.dialect = parser.value()?.parse()?;
+                }
+                Long("to") => match cli.subcommand {
+                    Some(Subcommand::Convert(ref mut to)) => *to = Some(parser.value()?.parse()?),
+                    _ => return Err(arg.unexpected()),
+                },
+                Short('t') | Long("timeout") => {
+                    cli.timeout = Some(parser.value()?.parse()?);
+                }
+                Short('f') | Long("file") => {
+                    cli.path = parser.value()?;
+                }
+                Value(val) => match cli.subcommand {
+                    Some(Subcommand::Explain(ref mut key)) if key.is_empty() => {
+                        *key = val.into_string()?;
+                    }
+                    Some(_) => return Ok(None),
+                    None if cli.command.is_none() => cli.command = Some(val),
+                    None => cli.args.push(val),
+                },
                 _ => return Err(arg.unexpected()),
             }
         }
 
-        Ok(cli)
+        Ok(Some(cli))
     }
 
     pub fn help_doc() -> String {
@@ -68,23 +253,59 @@ impl Cli {
 
 USAGE:
     {name} [FLAGS] [OPTIONS] -- <command> [args]...
+    {name} <subcommand> [FLAGS] [OPTIONS] [args]...
 
 FLAGS:
-    -v, --version       Prints version
+    -V, --version       Prints version
     -h, --help          Prints help information
     -x, --expand        Enable variable expansion
+    -c, --commands      Enable command substitution i.e. $(command), requires --expand
+        --files         Enable reading values from files i.e. KEY=@file:path
+        --file-keys     Enable reading KEY from the file of KEY_FILE=path
+        --includes      Enable including other env files i.e. #include path and source path
+        --utf16         Decode the env files starting with a UTF-16 BOM as UTF-16
+        --strip-prefix  Remove the --prefix from the keys e.g. API_PORT becomes PORT
+        --clear         Start the command with only the variables from the env file [alias: --isolated]
+    -E, --exec          Replace the zenv process with the command (unix only)
+        --spawn         Run the command as a child process [default]
+    -w, --watch         Restart the command when the env file changes
+    -n, --dry-run       Prints the command, working directory and environment changes without running
+    -v, --verbose       Logs the files read and the variables set, overridden or skipped
 
 OPTIONS:
-    -f, --file          Path to env file [default: .env]
+    -f, --file          Path to env file, - to read from stdin [default: .env]
+    -e, --env           Variable which overrides the env file e.g. KEY=VALUE, can be repeated
+        --env-string    Env data which overrides the env file e.g. 'A=1\\nB=2'
+    -d, --dialect       Syntax of the env files, zenv, bash, node, python, ruby, compose or docker [default: zenv]
+    -t, --timeout       Timeout for substituted commands in seconds [default: 10]
+    -k, --keep          Comma separated variables to inherit when using --clear e.g. PATH,HOME
+    -g, --grace         Seconds to wait for the command to exit before killing it [default: 5]
+        --prefix        Only load the variables whose keys start with the prefix e.g. API_
+        --only          Comma separated variables to load e.g. PORT,HOST
+        --exclude       Key pattern of the variables to skip e.g. *_SECRET, can be repeated
+    -s, --secret        Additional key pattern whose value is masked in the output e.g. *_KEY
 
 ARGS:
     <command>           Name of the command to execute
     [args]...           Arguments for the command
 
+SUBCOMMANDS:
+    explain <key>       Prints where the variable is defined and how its value came to be
+    compat-check        Prints the variables whose values differ between the dialects
+    convert --to <fmt>  Prints the variables as a docker-env or compose-env file
+
 Examples:
     {name} -- node index.js
     {name} -f .env.dev -- npm run dev
     {name} -f .env.prod -- terraform apply
+    {name} --clear -k PATH,HOME -- cargo test
+    {name} --watch -f .env -- cargo run
+    pass show app/env | {name} -f - -e DEBUG=1 -- ./app
+    {name} --dry-run -f .env.prod -- ./server
+    {name} --prefix API_ --strip-prefix -- ./api
+    {name} explain -x -f .env.dev DATABASE_URL
+    {name} compat-check -x -f .env
+    {name} convert --to docker-env -x -f .env > docker.env
 ",
             name = NAME,
             ver = VERSION,
@@ -0,0 +1,58 @@
+use std::collections::{BTreeSet, HashMap};
+use zenv::{Dialect, Value, MASK};
+
+pub type Parsed = HashMap<String, Value>;
+
+// Secret values are masked, the key is still reported if they differ
+fn show(val: Option<&Value>) -> String {
+    match val {
+        Some(v) if v.is_secret() => MASK.to_string(),
+        Some(v) => format!("{:?}", v.expose()),
+        None => "(not set)".to_string(),
+    }
+}
+
+/// Lists the keys whose values differ between the dialects, along with the value in each one of
+/// them. Returns `None` if all of them agree
+pub fn check(results: &[(Dialect, Result<Parsed, String>)]) -> Option<String> {
+    let mut out = Vec::new();
+
+    for (dialect, result) in results {
+        if let Err(e) = result {
+            out.push(format!("{} failed: {}", dialect, e));
+        }
+    }
+
+    let parsed: Vec<(Dialect, &Parsed)> = results
+        .iter()
+        .filter_map(|(d, r)| Some((*d, r.as_ref().ok()?)))
+        .collect();
+
+    let keys: BTreeSet<&String> = parsed.iter().flat_map(|(_, p)| p.keys()).collect();
+
+    for key in keys {
+        let values: Vec<Option<&str>> = parsed
+            .iter()
+            .map(|(_, p)| p.get(key).map(Value::expose))
+            .collect();
+
+        if values.iter().all(|x| *x == values[0]) {
+            continue;
+        }
+
+        out.push(key.to_string());
+
+        for (dialect, p) in &parsed {
+            out.push(format!(
+                "    {:<10}{}",
+                dialect.to_string(),
+                show(p.get(key))
+            ));
+        }
+    }
+
+    match out.is_empty() {
+        true => None,
+        false => Some(out.join("\n")),
+    }
+}
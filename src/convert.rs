@@ -0,0 +1,103 @@
+use std::{fmt, str::FromStr};
+
+/// Env file formats which the variables can be converted to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    // `docker run --env-file`, where the value is taken as it is
+    DockerEnv,
+    // Docker compose's `env_file`, where the values are double quoted
+    ComposeEnv,
+}
+
+impl Format {
+    const ALL: &'static [Format] = &[Self::DockerEnv, Self::ComposeEnv];
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::DockerEnv => "docker-env",
+            Self::ComposeEnv => "compose-env",
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|x| x.to_string() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<String> = Self::ALL.iter().map(Self::to_string).collect();
+                format!("Unknown format `{}`, expected {}", s, names.join(" or "))
+            })
+    }
+}
+
+// Docker trims the line and reads everything after the first `=` as the value, so the key can't
+// have a whitespace or `=`, and the value can't have a line break
+fn docker(key: &str, val: &str) -> Result<String, String> {
+    if key.is_empty() || key.starts_with('#') || key.contains(&[' ', '\t', '\r', '\n', '='][..]) {
+        return Err(format!(
+            "Key `{}` can't be written to a docker env file",
+            key
+        ));
+    }
+
+    if val.contains(&['\r', '\n'][..]) {
+        return Err(format!(
+            "Value of `{}` has a line break, which a docker env file can't represent",
+            key
+        ));
+    }
+
+    Ok(format!("{}={}", key, val))
+}
+
+// Double quotes keep the whitespace and `#`, and `$$` is a literal `$` as compose expands values
+fn compose(key: &str, val: &str) -> Result<String, String> {
+    let valid = |c: char| c.is_alphanumeric() || c == '_' || c == '.' || c == '-';
+
+    if key.is_empty() || !key.chars().all(valid) {
+        return Err(format!(
+            "Key `{}` can't be written to a compose env file",
+            key
+        ));
+    }
+
+    let mut quoted = String::with_capacity(val.len() + 2);
+
+    for c in val.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("$$"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+
+    Ok(format!("{}=\"{}\"", key, quoted))
+}
+
+/// Writes the variables in the format, one per line, so that the tool reads the same values.
+/// Fails on the first variable which the format can't represent
+pub fn convert(vars: &[(String, String)], format: Format) -> Result<String, String> {
+    let line = match format {
+        Format::DockerEnv => docker,
+        Format::ComposeEnv => compose,
+    };
+
+    let mut out = String::new();
+
+    for (key, val) in vars {
+        out.push_str(&line(key, val)?);
+        out.push('\n');
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,213 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    io::{Error, ErrorKind, Result},
+    str,
+};
+
+use crate::MASK;
+
+/// Invalid bytes are kept in the lossless mode as this noncharacter followed by `U+10FF00 + byte`,
+/// so that they survive the parsing and can be restored later. The noncharacter itself is kept as
+/// two of them, so the chars in the file are never mistaken for the invalid bytes
+const ESCAPE: char = '\u{FDD0}';
+
+const SENTINEL: u32 = 0x10FF00;
+
+/// How the invalid UTF-8 in the env data is handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Mode {
+    /// Invalid UTF-8 is an error
+    Strict,
+    /// Invalid bytes are replaced with `U+FFFD`
+    Lossy,
+    /// Invalid bytes are kept, see [`encode`]
+    Lossless,
+}
+
+/// Decodes the env files, and the env data, according to the options of `Zenv`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Decoder {
+    pub(crate) mode: Mode,
+    /// Whether to decode the content starting with a UTF-16 BOM as UTF-16
+    pub(crate) utf16: bool,
+}
+
+impl Decoder {
+    /// Decode the bytes as UTF-8, or as UTF-16 if enabled, without the BOM. `name` is used in the
+    /// errors
+    pub(crate) fn decode(&self, bytes: &[u8], name: &str) -> Result<String> {
+        let (bytes, be) = match bytes {
+            [0xEF, 0xBB, 0xBF, rest @ ..] => return decode(rest, self.mode, name),
+            [0xFF, 0xFE, rest @ ..] => (rest, false),
+            [0xFE, 0xFF, rest @ ..] => (rest, true),
+            _ => return decode(bytes, self.mode, name),
+        };
+
+        if !self.utf16 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is UTF-16, which needs to be enabled", name),
+            ));
+        }
+
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is not valid UTF-16", name),
+            )
+        };
+
+        if bytes.len() % 2 != 0 {
+            return Err(invalid());
+        }
+
+        let units = bytes.chunks(2).map(|x| match be {
+            true => u16::from_be_bytes([x[0], x[1]]),
+            false => u16::from_le_bytes([x[0], x[1]]),
+        });
+
+        let text = char::decode_utf16(units)
+            .collect::<std::result::Result<String, _>>()
+            .map_err(|_| invalid())?;
+
+        Ok(match self.mode {
+            Mode::Lossless => text.replace(ESCAPE, "\u{FDD0}\u{FDD0}"),
+            _ => text,
+        })
+    }
+}
+
+/// Env data which is not read from a file, it is never printed as it may contain secrets
+#[derive(Clone)]
+pub(crate) struct Data(pub(crate) Vec<u8>);
+
+impl fmt::Debug for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+/// Decode the bytes as UTF-8 according to the [`Mode`], `name` is used in the error which points
+/// to the first invalid byte
+fn decode(bytes: &[u8], mode: Mode, name: &str) -> Result<String> {
+    if mode == Mode::Strict {
+        return match str::from_utf8(bytes) {
+            Ok(text) => Ok(text.to_string()),
+            Err(e) => Err(not_utf8(&bytes[..e.valid_up_to()], name)),
+        };
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut rest = bytes;
+
+    loop {
+        let (valid, invalid, next) = match str::from_utf8(rest) {
+            Ok(valid) => (valid, &[][..], &[][..]),
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                let len = e.error_len().unwrap_or(invalid.len());
+
+                let valid = str::from_utf8(valid).unwrap_or_default();
+                (valid, &invalid[..len], &invalid[len..])
+            }
+        };
+
+        for c in valid.chars() {
+            if mode == Mode::Lossless && c == ESCAPE {
+                text.push(ESCAPE);
+            }
+            text.push(c);
+        }
+
+        for b in invalid {
+            match mode {
+                Mode::Lossless => {
+                    text.push(ESCAPE);
+                    text.extend(char::from_u32(SENTINEL + *b as u32));
+                }
+                _ => text.push(char::REPLACEMENT_CHARACTER),
+            }
+        }
+
+        if next.is_empty() {
+            return Ok(text);
+        }
+
+        rest = next;
+    }
+}
+
+/// Error which points to the invalid byte after the `valid` bytes
+fn not_utf8(valid: &[u8], name: &str) -> Error {
+    // Valid bytes are always valid UTF-8
+    let valid = str::from_utf8(valid).unwrap_or_default();
+    let line = valid.matches('\n').count() + 1;
+    let column = valid
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+
+    Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "{} is not valid UTF-8 - invalid byte at line {}, column {}",
+            name, line, column
+        ),
+    )
+}
+
+/// Chars of the text decoded in the lossless mode, where the invalid bytes are `Err`
+fn chars(text: &str) -> impl Iterator<Item = std::result::Result<char, u8>> + '_ {
+    let mut chars = text.chars().peekable();
+
+    std::iter::from_fn(move || {
+        let c = chars.next()?;
+        let next = chars.peek().copied().filter(|_| c == ESCAPE);
+        let byte = |x: char| (x as u32).checked_sub(SENTINEL).filter(|x| *x <= 0xFF);
+
+        match next.map(|x| (x, byte(x))) {
+            Some((ESCAPE, _)) => {
+                chars.next();
+                Some(Ok(ESCAPE))
+            }
+            Some((_, Some(b))) => {
+                chars.next();
+                Some(Err(b as u8))
+            }
+            _ => Some(Ok(c)),
+        }
+    })
+}
+
+/// Restores the invalid bytes kept by [`decode`]. Only unix allows arbitrary bytes, so they are
+/// replaced with `U+FFFD` everywhere else
+pub(crate) fn encode(text: String) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut bytes = Vec::with_capacity(text.len());
+
+        for c in chars(&text) {
+            match c {
+                Ok(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                Err(b) => bytes.push(b),
+            }
+        }
+
+        OsString::from_vec(bytes)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let text: String = chars(&text)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+
+        OsString::from(text)
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{Secrets, MASK};
+
+/// Variables which are embedded into the binary at compile time using [`include!`](crate::include)
+///
+/// The [`Debug`](fmt::Debug) output masks the values of the keys which look like secrets, or are
+/// annotated with the `# @secret` comment.
+///
+/// Example
+/// ```
+/// static ENV: zenv::Embedded = zenv::include!("tests/include/.env", includes);
+///
+/// assert_eq!(ENV.get("COMMON"), Some("common"));
+/// assert_eq!(ENV.get("OVERRIDDEN"), Some("overridden"));
+/// assert_eq!(ENV.vars()[0], ("COMMON", "common"));
+/// ```
+///
+/// Malformed env files fail the compilation
+/// ```compile_fail
+/// static ENV: zenv::Embedded = zenv::include!("tests/.env.malformed");
+/// ```
+#[derive(Clone, Copy)]
+pub struct Embedded {
+    vars: &'static [(&'static str, &'static str)],
+    // Keys which are annotated with `# @secret` comment
+    marked: &'static [&'static str],
+}
+
+impl Embedded {
+    #[doc(hidden)]
+    pub const fn new(
+        vars: &'static [(&'static str, &'static str)],
+        marked: &'static [&'static str],
+    ) -> Self {
+        Self { vars, marked }
+    }
+
+    /// Variables in the order they are defined, without duplicates
+    pub fn vars(&self) -> &'static [(&'static str, &'static str)] {
+        self.vars
+    }
+
+    /// Value of the variable, if it is defined
+    pub fn get(&self, key: &str) -> Option<&'static str> {
+        self.vars.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+
+    /// Variables as a hashmap, same as [`Zenv::parse`](crate::Zenv::parse)
+    pub fn to_hash_map(&self) -> HashMap<String, String> {
+        self.vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    /// Sets the environment variables, same as [`Zenv::configure`](crate::Zenv::configure)
+    pub fn configure(&self) {
+        for (key, val) in self.vars {
+            std::env::set_var(key, val);
+        }
+    }
+}
+
+impl fmt::Debug for Embedded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secrets = Secrets::default();
+
+        f.debug_map()
+            .entries(
+                self.vars
+                    .iter()
+                    .map(|(k, v)| match self.marked.contains(k) {
+                        true => (k, MASK),
+                        false => (k, secrets.mask(k, v)),
+                    }),
+            )
+            .finish()
+    }
+}
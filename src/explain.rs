@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use zenv::{Origin, Quote, Value, MASK};
+
+type Parsed = HashMap<String, (Value, Origin)>;
+
+fn quote(q: Quote) -> &'static str {
+    match q {
+        Quote::Single => "single quoted",
+        Quote::Double => "double quoted",
+        Quote::No => "unquoted",
+    }
+}
+
+// Raw line can also contain the secret, so it needs to be masked as well
+fn raw(key: &str, val: &Value, origin: &Origin) -> String {
+    match val.is_secret() {
+        true => format!("{}={}", key, MASK),
+        false => origin.raw.trim().to_string(),
+    }
+}
+
+fn describe(
+    key: &str,
+    parsed: &Parsed,
+    depth: usize,
+    seen: &mut Vec<String>,
+    out: &mut Vec<String>,
+) {
+    let pad = "    ".repeat(depth);
+
+    let (val, origin) = match parsed.get(key) {
+        Some(x) => x,
+        None if key.starts_with("$(") => {
+            out.push(format!("{}{} is a substituted command", pad, key));
+            return;
+        }
+        None => {
+            out.push(format!("{}{} is taken from the environment", pad, key));
+            return;
+        }
+    };
+
+    out.push(format!("{}{}={}", pad, key, val));
+    out.push(format!(
+        "{}  defined at {} ({})",
+        pad,
+        origin,
+        quote(origin.quote)
+    ));
+    out.push(format!("{}    {}", pad, raw(key, val, origin)));
+
+    for o in origin.overrides.iter().rev() {
+        out.push(format!("{}  overrides {} ({})", pad, o, quote(o.quote)));
+        out.push(format!("{}    {}", pad, raw(key, val, o)));
+    }
+
+    if origin.is_expanded() {
+        out.push(format!("{}  expanded from {}", pad, origin.refs.join(", ")));
+
+        for r in &origin.refs {
+            // Guard against the variables which refer to themselves
+            if seen.contains(r) {
+                continue;
+            }
+
+            seen.push(r.to_string());
+            describe(r, parsed, depth + 1, seen, out);
+        }
+    }
+}
+
+/// Describes where the variable is defined and how its value came to be
+pub fn explain(key: &str, parsed: &Parsed) -> Result<String, String> {
+    if !parsed.contains_key(key) {
+        return Err(format!("`{}` is not defined", key));
+    }
+
+    let mut out = Vec::new();
+    describe(key, parsed, 0, &mut vec![key.to_string()], &mut out);
+
+    Ok(out.join("\n"))
+}
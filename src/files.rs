@@ -0,0 +1,85 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the value which should be read from a file
+pub(crate) const FILE_PREFIX: &str = "@file:";
+
+/// Suffix of the key whose value is a path to the file containing the actual value
+pub(crate) const FILE_SUFFIX: &str = "_FILE";
+
+fn read(base: &Path, path: &str, key: &str, read: &mut Vec<PathBuf>) -> Result<String> {
+    // Relative paths are resolved against the directory of the env file which defines the key
+    let path = base.join(path);
+
+    let mut val = read_to_string(&path).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!(
+                "Unable to read file - {} referenced by `{}` - {}",
+                path.display(),
+                key,
+                e
+            ),
+        )
+    })?;
+
+    while val.ends_with('\n') || val.ends_with('\r') {
+        val.pop();
+    }
+
+    if !read.contains(&path) {
+        read.push(path);
+    }
+
+    Ok(val)
+}
+
+/// Replace `KEY=@file:path` with the content of the file if `prefix` is enabled, and for every
+/// `KEY_FILE=path` sets the `KEY` with the content of the file if `suffix` is enabled. Trailing
+/// newlines are trimmed from the content. `base` returns the directory which the paths of the key
+/// are relative to. Returns the paths of the files which were read
+pub(crate) fn resolve<F>(
+    vars: &mut HashMap<String, String>,
+    prefix: bool,
+    suffix: bool,
+    base: F,
+) -> Result<Vec<PathBuf>>
+where
+    F: Fn(&str) -> PathBuf,
+{
+    let mut read_files = Vec::new();
+    let mut keys: Vec<String> = vars.keys().cloned().collect();
+
+    // Sorting to keep the errors deterministic
+    keys.sort();
+
+    for key in keys.iter().filter(|_| prefix) {
+        if let Some(path) = vars[key].strip_prefix(FILE_PREFIX) {
+            let val = read(&base(key), path.trim(), key, &mut read_files)?;
+            vars.insert(key.to_string(), val);
+        }
+    }
+
+    for key in keys.iter().filter(|_| suffix) {
+        let target = match key.strip_suffix(FILE_SUFFIX) {
+            Some(x) if !x.is_empty() => x,
+            _ => continue,
+        };
+
+        if vars.contains_key(target) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Both `{}` and `{}` are set", target, key),
+            ));
+        }
+
+        let val = read(&base(key), &vars[key], key, &mut read_files)?;
+        vars.insert(target.to_string(), val);
+    }
+
+    Ok(read_files)
+}
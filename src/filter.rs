@@ -0,0 +1,39 @@
+use zenv_core::glob;
+
+/// Which variables are kept after the parsing, and how their keys are renamed
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Filter {
+    pub(crate) prefix: Option<String>,
+    pub(crate) strip: bool,
+    pub(crate) only: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+}
+
+impl Filter {
+    /// Whether the variable is kept, the key is matched before the prefix is stripped
+    pub(crate) fn keep(&self, key: &str) -> bool {
+        if let Some(prefix) = &self.prefix {
+            // Stripping the prefix from the key `PREFIX` itself leaves an empty key
+            if !key.starts_with(prefix.as_str()) || (self.strip && key == prefix) {
+                return false;
+            }
+        }
+
+        if !self.only.is_empty() && !self.only.iter().any(|x| x == key) {
+            return false;
+        }
+
+        !self.exclude.iter().any(|p| glob(p, key))
+    }
+
+    /// Strip the prefix from the key, if enabled
+    pub(crate) fn rename(&self, key: String) -> String {
+        match &self.prefix {
+            Some(prefix) if self.strip => match key.strip_prefix(prefix.as_str()) {
+                Some(x) => x.to_string(),
+                None => key,
+            },
+            _ => key,
+        }
+    }
+}
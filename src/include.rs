@@ -0,0 +1,130 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use crate::{decode::Decoder, Dialect, Keys, Lines};
+
+/// Content of an env file, `path` is `None` if the content is not read from a file
+pub(crate) struct Source {
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) text: String,
+}
+
+/// Included paths are relative to the file which includes them
+fn resolve(file: Option<&Path>, include: &str) -> PathBuf {
+    file.and_then(Path::parent)
+        .unwrap_or_else(|| Path::new(""))
+        .join(include)
+}
+
+/// Read the file and all the files included by it, depth first. `stack` contains the files which
+/// are currently being read, which is used to detect the include cycles
+pub(crate) fn load(
+    path: &Path,
+    decoder: Decoder,
+    dialect: Dialect,
+    includes: bool,
+    stack: &mut Vec<PathBuf>,
+    sources: &mut Vec<Source>,
+) -> Result<()> {
+    if !path.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Unable to find file - {}", path.display()),
+        ));
+    }
+
+    let canonical = path.canonicalize()?;
+
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(Some(&canonical))
+            .map(|x| x.display().to_string())
+            .collect();
+
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Include cycle detected - {}", chain.join(" -> ")),
+        ));
+    }
+
+    let text = decoder.decode(&fs::read(path)?, &path.display().to_string())?;
+
+    stack.push(canonical);
+    load_text(Some(path), text, decoder, dialect, includes, stack, sources)?;
+    stack.pop();
+
+    Ok(())
+}
+
+/// Same as [`load`] but the content is already read. Without a `path`, included paths are
+/// relative to the current directory
+pub(crate) fn load_text(
+    path: Option<&Path>,
+    text: String,
+    decoder: Decoder,
+    dialect: Dialect,
+    includes: bool,
+    stack: &mut Vec<PathBuf>,
+    sources: &mut Vec<Source>,
+) -> Result<()> {
+    let paths: Vec<PathBuf> = match includes {
+        true => Lines::includes(&text, dialect)
+            .into_iter()
+            .map(|x| resolve(path, x))
+            .collect(),
+        false => Vec::new(),
+    };
+
+    sources.push(Source {
+        path: path.map(Path::to_path_buf),
+        text,
+    });
+
+    for include in paths {
+        load(&include, decoder, dialect, includes, stack, sources)?;
+    }
+
+    Ok(())
+}
+
+fn walk<'l>(
+    sources: &'l [Source],
+    includes: bool,
+    next: &mut usize,
+    lines: &mut Lines<'l>,
+) -> Result<()> {
+    let source = &sources[*next];
+    *next += 1;
+
+    lines.read(
+        &source.text,
+        source.path.as_deref(),
+        &mut |lines, _| match includes {
+            true => walk(sources, includes, next, lines),
+            false => Ok(()),
+        },
+    )
+}
+
+/// Create lines from the sources loaded by [`load`], included lines are placed at the position
+/// of the include so that the later definitions override the earlier ones. Include lines are
+/// ignored if the `includes` are disabled
+pub(crate) fn lines(
+    sources: &[Source],
+    keys: Keys,
+    dialect: Dialect,
+    includes: bool,
+) -> Result<Lines<'_>> {
+    let mut lines = Lines::with_options(keys, dialect);
+
+    if !sources.is_empty() {
+        // Sources are loaded in the same order as they are visited
+        walk(sources, includes, &mut 0, &mut lines)?;
+    }
+
+    Ok(lines)
+}
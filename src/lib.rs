@@ -21,36 +21,520 @@
 //!     zenv!(".env.development", true);
 //! }
 //! ```
+//!
+//! Values of keys which look like secrets (`*_SECRET`, `*_TOKEN`, `*PASSWORD*`) or are annotated
+//! with a `# @secret` comment can be wrapped into a [`Secret`] using [`Zenv::parse_redacted`],
+//! so that they never end up in your logs.
+//!
+//! With the `macros` feature, [`include!`] embeds the env file into the binary at compile time.
+//! Build scripts can also forward the variables to the compiler using [`build::emit`].
 
+pub mod build;
+mod decode;
+#[cfg(feature = "macros")]
+mod embed;
+mod files;
+mod filter;
+mod include;
 mod parser;
+mod reload;
+mod scoped;
+mod snapshot;
 
 use std::{
     collections::HashMap,
-    fs::read_to_string,
-    io::{Error, ErrorKind, Result},
-    path::PathBuf,
+    ffi::OsString,
+    io::{Error, Read, Result},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
+use decode::{Data, Decoder, Mode};
+use filter::Filter;
+use include::Source;
+use parser::Refs;
+
+/// Variables, the references used in each value and the paths of the files the values are read from
+type Resolved = (HashMap<String, String>, Refs, Vec<PathBuf>);
+
+/// Variables in the order they are defined
+type Ordered = Vec<(String, String)>;
+
 // Just re-exporting to use as a standalone parser
-pub use parser::{KeyVal, Line, Lines, Quote};
+pub use parser::{Dialect, Duplicates, KeyPolicy, Keys, Lines, Origin};
+pub use reload::{Change, Watcher};
+pub use scoped::Scoped;
+pub use snapshot::{var, vars};
+pub use zenv_core::{KeyVal, Line, Quote, Secret, Secrets, Value, DEFAULT_PATTERNS, MASK};
+
+#[cfg(feature = "macros")]
+pub use embed::Embedded;
+#[cfg(feature = "macros")]
+pub use zenv_macros::include;
 
 /// Use this to load and configure the environment variables
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Zenv {
     path: PathBuf,
+    // Content of the env file when it is not read from the path
+    data: Option<Data>,
     expand: bool,
+    commands: bool,
+    timeout: Duration,
+    files: bool,
+    file_keys: bool,
+    includes: bool,
+    lossy: bool,
+    utf16: bool,
+    keys: Keys,
+    dialect: Dialect,
+    filter: Filter,
+    secrets: Secrets,
 }
 
 impl Zenv {
     /// Create a new instance of Zenv with the provided file path
-    pub fn new(path: &str, expand: bool) -> Self {
+    pub fn new<P: AsRef<Path>>(path: P, expand: bool) -> Self {
         Self {
-            path: PathBuf::from(path),
+            path: path.as_ref().to_path_buf(),
+            data: None,
             expand,
+            commands: false,
+            timeout: Duration::from_secs(10),
+            files: false,
+            file_keys: false,
+            includes: false,
+            lossy: false,
+            utf16: false,
+            keys: Keys::default(),
+            dialect: Dialect::default(),
+            filter: Filter::default(),
+            secrets: Secrets::default(),
+        }
+    }
+
+    /// Create a new instance of Zenv from the env data in memory, instead of a file e.g. the one
+    /// embedded using [`include_str!`]. Included paths, and the paths of the
+    /// [file values](Zenv::files), are relative to the current directory.
+    ///
+    /// Example
+    /// ```
+    /// let data = "BASIC=basic\nEXPANDED=\"${BASIC}_expanded\"";
+    ///
+    /// let parsed = zenv::Zenv::from_str(data, true).parse().unwrap();
+    ///
+    /// assert_eq!(parsed.get("EXPANDED"), Some(&"basic_expanded".to_string()))
+    /// ```
+    pub fn from_str(text: &str, expand: bool) -> Self {
+        Self {
+            data: Some(Data(text.as_bytes().to_vec())),
+            ..Self::new("", expand)
+        }
+    }
+
+    /// Same as [Zenv::from_str] but the env data is in bytes. Invalid UTF-8 is an error, which
+    /// contains the line and the column of the first invalid byte, when the data is parsed into
+    /// strings. See [Zenv::lossy] and [Zenv::parse_os] to avoid that.
+    ///
+    /// Example
+    /// ```
+    /// let zenv = zenv::Zenv::from_bytes(b"BASIC=basic\nINVALID=\xff", false);
+    /// let err = zenv.parse().unwrap_err();
+    ///
+    /// assert_eq!(err.to_string(), "Env data is not valid UTF-8 - invalid byte at line 2, column 9");
+    /// ```
+    pub fn from_bytes(bytes: &[u8], expand: bool) -> Self {
+        Self {
+            data: Some(Data(bytes.to_vec())),
+            ..Self::new("", expand)
+        }
+    }
+
+    /// Same as [Zenv::from_bytes] but the env data is read from the `reader` e.g. stdin
+    ///
+    /// Example
+    /// ```
+    /// let data = "BASIC=basic";
+    ///
+    /// let parsed = zenv::Zenv::from_reader(data.as_bytes(), false)
+    ///     .unwrap()
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("BASIC"), Some(&"basic".to_string()))
+    /// ```
+    pub fn from_reader<R: Read>(mut reader: R, expand: bool) -> Result<Self> {
+        let mut bytes = Vec::new();
+
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|e| Error::new(e.kind(), format!("Unable to read env data - {}", e)))?;
+
+        Ok(Self::from_bytes(&bytes, expand))
+    }
+
+    /// Enable command substitution i.e. `$(command)` inside double quoted values. Only works if
+    /// the variable expansion is also enabled. See [`Lines::expand_commands`]
+    ///
+    /// _Only enable this for files you trust as it executes arbitrary commands._
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.command", true)
+    ///     .commands(true)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("COMMAND"), Some(&"basic_command".to_string()))
+    /// ```
+    pub fn commands(mut self, enable: bool) -> Self {
+        self.commands = enable;
+        self
+    }
+
+    /// Maximum time a substituted command is allowed to run. Defaults to 10 seconds
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Enable reading values from files i.e. `KEY=@file:path` sets `KEY` to the content of the
+    /// file, which is disabled by default. Relative paths are resolved against the directory of
+    /// the env file and trailing newlines are trimmed from the content. It is an error if the file
+    /// can't be read
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.files", false)
+    ///     .files(true)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("INLINE"), Some(&"from_file".to_string()));
+    /// ```
+    pub fn files(mut self, enable: bool) -> Self {
+        self.files = enable;
+        self
+    }
+
+    /// Enable setting `KEY` to the content of the file for every `KEY_FILE=path`, which is a common
+    /// convention for docker and kubernetes secrets. Disabled by default, as keys like `LOG_FILE`
+    /// are usually just paths. The paths are read same as [Zenv::files], and it is an error if the
+    /// file can't be read or `KEY` is also defined
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.files", false)
+    ///     .file_keys(true)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("DB_PASSWORD"), Some(&"from_file".to_string()));
+    /// ```
+    pub fn file_keys(mut self, enable: bool) -> Self {
+        self.file_keys = enable;
+        self
+    }
+
+    /// Enable including other env files using `#include <path>`, `source <path>` or `. <path>`,
+    /// where the path is relative to the including file. Included variables are placed at the
+    /// position of the include, so the variables defined later override them. Disabled by
+    /// default, in which case these lines are ignored as `#include` is also a comment
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/include/.env", false)
+    ///     .includes(true)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("LOCAL"), Some(&"local".to_string()));
+    /// ```
+    pub fn includes(mut self, enable: bool) -> Self {
+        self.includes = enable;
+        self
+    }
+
+    /// Replace the invalid UTF-8 in the env files with `U+FFFD`, instead of failing, when the
+    /// variables are parsed into strings. Disabled by default
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::from_bytes(b"INVALID=in\xffvalid", false)
+    ///     .lossy(true)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("INVALID"), Some(&"in\u{FFFD}valid".to_string()));
+    /// ```
+    pub fn lossy(mut self, enable: bool) -> Self {
+        self.lossy = enable;
+        self
+    }
+
+    /// Decode the env files which start with a UTF-16 byte order mark as UTF-16, which is what
+    /// some Windows editors write. Disabled by default, so such files are an error
+    ///
+    /// Example
+    /// ```
+    /// let bytes: Vec<u8> = std::iter::once(0xFEFF)
+    ///     .chain("BASIC=basic".encode_utf16())
+    ///     .flat_map(u16::to_le_bytes)
+    ///     .collect();
+    ///
+    /// let zenv = zenv::Zenv::from_bytes(&bytes, false);
+    ///
+    /// assert!(zenv.parse().is_err());
+    ///
+    /// let parsed = zenv.utf16(true).parse().unwrap();
+    ///
+    /// assert_eq!(parsed.get("BASIC"), Some(&"basic".to_string()));
+    /// ```
+    pub fn utf16(mut self, enable: bool) -> Self {
+        self.utf16 = enable;
+        self
+    }
+
+    /// Validate and normalize the keys, by default any key is accepted as it is. Normalized keys
+    /// are used everywhere, including the variable expansion. See [`Keys`]
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{KeyPolicy, Keys, Zenv};
+    ///
+    /// let zenv = Zenv::from_str("app.port=5000\nMY KEY=1", false);
+    ///
+    /// let parsed = zenv
+    ///     .clone()
+    ///     .keys(Keys {
+    ///         policy: KeyPolicy::Posix,
+    ///         uppercase: true,
+    ///         underscore: true,
+    ///         ..Keys::default()
+    ///     })
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("APP_PORT"), Some(&"5000".to_string()));
+    /// assert_eq!(parsed.get("MY KEY"), None);
+    ///
+    /// let keys = Keys {
+    ///     policy: KeyPolicy::Error,
+    ///     ..Keys::default()
+    /// };
+    ///
+    /// assert!(zenv.keys(keys).parse().is_err());
+    /// ```
+    pub fn keys(mut self, keys: Keys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// How the keys defined multiple times in the same file are handled, which is a shortcut for
+    /// setting [`Keys::duplicates`]. Defaults to [`Duplicates::LastWins`]
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{Duplicates, Zenv};
+    ///
+    /// let zenv = Zenv::from_str("PORT=5000\nHOST=localhost\nPORT=8080", false);
+    ///
+    /// let parsed = zenv.clone().duplicates(Duplicates::FirstWins).parse().unwrap();
+    /// assert_eq!(parsed.get("PORT"), Some(&"5000".to_string()));
+    ///
+    /// let err = zenv.duplicates(Duplicates::Error).parse().unwrap_err();
+    /// assert_eq!(err.to_string(), "Line 3: Duplicate key `PORT`, first defined at line 1");
+    /// ```
+    pub fn duplicates(mut self, duplicates: Duplicates) -> Self {
+        self.keys.duplicates = duplicates;
+        self
+    }
+
+    /// Syntax of the env files, defaults to [`Dialect::Zenv`]. Use [`Dialect::Bash`] so that the
+    /// file means the same as it does with `source .env`
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{Dialect, Zenv};
+    ///
+    /// let zenv = Zenv::from_str("URL=localhost#main\nQUOTED=\"a\"'b'", false);
+    ///
+    /// let parsed = zenv.clone().parse().unwrap();
+    /// assert_eq!(parsed.get("URL"), Some(&"localhost".to_string()));
+    ///
+    /// let parsed = zenv.dialect(Dialect::Bash).parse().unwrap();
+    /// assert_eq!(parsed.get("URL"), Some(&"localhost#main".to_string()));
+    /// assert_eq!(parsed.get("QUOTED"), Some(&"ab".to_string()));
+    /// ```
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Only keep the variables whose keys start with the `prefix`, which is handy when a single
+    /// env file is shared by multiple services. Variables without the prefix can still be used in
+    /// the variable expansion
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::from_str("HOST=localhost\nAPI_URL=\"${HOST}/api\"", true)
+    ///     .prefix("API_")
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("API_URL"), Some(&"localhost/api".to_string()));
+    /// assert_eq!(parsed.get("HOST"), None);
+    /// ```
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.filter.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Remove the [prefix](Zenv::prefix) from the keys. Disabled by default
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::from_str("API_PORT=5000\nWORKER_PORT=5001", false)
+    ///     .prefix("API_")
+    ///     .strip_prefix(true)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("PORT"), Some(&"5000".to_string()));
+    /// assert_eq!(parsed.len(), 1);
+    /// ```
+    pub fn strip_prefix(mut self, enable: bool) -> Self {
+        self.filter.strip = enable;
+        self
+    }
+
+    /// Only keep the variables with these keys. Keys are matched before the prefix is stripped
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.basic", false)
+    ///     .only(&["BASIC"])
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.len(), 1);
+    /// ```
+    pub fn only(mut self, keys: &[&str]) -> Self {
+        self.filter.only = keys.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    /// Skip the variables whose keys match any of the patterns, where `*` matches any sequence
+    /// of characters. Keys are matched before the prefix is stripped
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.secret", false)
+    ///     .exclude(&["*_TOKEN", "*_KEY"])
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("GITHUB_TOKEN"), None);
+    /// assert_eq!(parsed.get("API_KEY"), None);
+    /// ```
+    pub fn exclude(mut self, patterns: &[&str]) -> Self {
+        self.filter.exclude = patterns.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    /// Override the key patterns which are considered as secrets. Defaults to [`DEFAULT_PATTERNS`]
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.secret", false)
+    ///     .secrets(&["*_KEY"])
+    ///     .parse_redacted()
+    ///     .unwrap();
+    ///
+    /// assert!(parsed["API_KEY"].is_secret());
+    /// assert!(!parsed["GITHUB_TOKEN"].is_secret());
+    /// ```
+    pub fn secrets(mut self, patterns: &[&str]) -> Self {
+        self.secrets = Secrets::new(patterns);
+        self
+    }
+
+    fn read(&self) -> Result<Vec<Source>> {
+        self.read_with(if self.lossy {
+            Mode::Lossy
+        } else {
+            Mode::Strict
+        })
+    }
+
+    fn read_with(&self, mode: Mode) -> Result<Vec<Source>> {
+        let mut sources = Vec::new();
+        let decoder = Decoder {
+            mode,
+            utf16: self.utf16,
+        };
+
+        match &self.data {
+            Some(data) => {
+                let text = decoder.decode(&data.0, "Env data")?;
+                include::load_text(
+                    None,
+                    text,
+                    decoder,
+                    self.dialect,
+                    self.includes,
+                    &mut Vec::new(),
+                    &mut sources,
+                )?
+            }
+            None => include::load(
+                &self.path,
+                decoder,
+                self.dialect,
+                self.includes,
+                &mut Vec::new(),
+                &mut sources,
+            )?,
         }
+
+        Ok(sources)
+    }
+
+    /// Returns the variables along with the references used in each value
+    fn resolve(&self, lines: &Lines) -> Result<(HashMap<String, String>, Refs)> {
+        self.resolve_files(lines)
+            .map(|(hash, refs, _)| (hash, refs))
     }
 
-    /// Read and parse the file from provided path and returns a hashmap
+    /// Same as [Zenv::resolve] along with the paths of the files which the values are read from
+    fn resolve_files(&self, lines: &Lines) -> Result<Resolved> {
+        let mut read = Vec::new();
+        let (mut hash, refs) = match self.expand {
+            true => lines.expand_traced(Some(self.timeout).filter(|_| self.commands))?,
+            false => (lines.to_hash_map(), HashMap::new()),
+        };
+
+        if self.files || self.file_keys {
+            let root = self.path.parent().unwrap_or_else(|| Path::new(""));
+            let origins = lines.origins();
+
+            read = files::resolve(&mut hash, self.files, self.file_keys, |key| {
+                origins
+                    .get(key)
+                    .and_then(|o| o.file.as_deref())
+                    .and_then(Path::parent)
+                    .unwrap_or(root)
+                    .to_path_buf()
+            })?;
+        }
+
+        hash.retain(|k, _| self.filter.keep(k));
+
+        Ok((hash, refs, read))
+    }
+
+    /// Read and parse the file from provided path and returns a hashmap. Other files are only
+    /// included if it is [enabled](Zenv::includes)
     ///
     /// Example
     /// ```
@@ -59,27 +543,277 @@ impl Zenv {
     /// assert_eq!(parsed.get("BASIC"), Some(&"basic".to_string()))
     /// ```
     pub fn parse(&self) -> Result<HashMap<String, String>> {
-        let path = &self.path;
+        let sources = self.read()?;
+        let lines = include::lines(&sources, self.keys, self.dialect, self.includes)?;
+
+        let (hash, _) = self.resolve(&lines)?;
+
+        Ok(hash
+            .into_iter()
+            .map(|(k, v)| (self.filter.rename(k), v))
+            .collect())
+    }
+
+    /// Same as [Zenv::parse] but the keys and the values are [`OsString`], so that the invalid
+    /// UTF-8 in the env files is kept as it is. Only unix allows arbitrary bytes in the
+    /// environment, so they are replaced with `U+FFFD` everywhere else
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::from_bytes(b"INVALID=in\xffvalid", false)
+    ///     .parse_os()
+    ///     .unwrap();
+    ///
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::os::unix::ffi::OsStrExt;
+    ///
+    /// assert_eq!(parsed[std::ffi::OsStr::new("INVALID")].as_bytes(), b"in\xffvalid");
+    /// # }
+    /// ```
+    pub fn parse_os(&self) -> Result<HashMap<OsString, OsString>> {
+        let sources = self.read_with(Mode::Lossless)?;
+        let lines = include::lines(&sources, self.keys, self.dialect, self.includes)?;
+        let (hash, _) = self.resolve(&lines)?;
+
+        Ok(hash
+            .into_iter()
+            .map(|(k, v)| (decode::encode(self.filter.rename(k)), decode::encode(v)))
+            .collect())
+    }
+
+    /// Same as [Zenv::parse] but the variables are in the order they are defined. See
+    /// [`Lines::ordered`]
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.basic", false).parse_ordered().unwrap();
+    ///
+    /// assert_eq!(parsed[0], ("BASIC".to_string(), "basic".to_string()));
+    /// assert_eq!(parsed[1], ("EMPTY".to_string(), "".to_string()));
+    /// ```
+    pub fn parse_ordered(&self) -> Result<Vec<(String, String)>> {
+        self.parse_ordered_paths().map(|(vars, _)| vars)
+    }
+
+    /// Same as [Zenv::parse_ordered] along with the paths of every file which is read i.e. the env
+    /// file, the included files and the files which the values are read from
+    pub(crate) fn parse_ordered_paths(&self) -> Result<(Ordered, Vec<PathBuf>)> {
+        let sources = self.read()?;
+        let lines = include::lines(&sources, self.keys, self.dialect, self.includes)?;
+        let (hash, _, read) = self.resolve_files(&lines)?;
+
+        let vars = lines
+            .ordered(hash)
+            .into_iter()
+            .map(|(k, v)| (self.filter.rename(k), v))
+            .collect();
+
+        let mut paths: Vec<PathBuf> = sources.into_iter().filter_map(|x| x.path).collect();
+        paths.extend(read);
+
+        Ok((vars, paths))
+    }
+
+    /// Same as [Zenv::parse_ordered] but the values are wrapped into [`Value`]
+    pub(crate) fn parse_ordered_redacted(&self) -> Result<Vec<(String, Value)>> {
+        let sources = self.read()?;
+        let lines = include::lines(&sources, self.keys, self.dialect, self.includes)?;
+        let (hash, _) = self.resolve(&lines)?;
+
+        Ok(lines
+            .ordered(lines.redact(hash, &self.secrets))
+            .into_iter()
+            .map(|(k, v)| (self.filter.rename(k), v))
+            .collect())
+    }
+
+    /// Watch the env file, and the files included by it, for changes. Whenever a file changes it
+    /// is parsed again and the `callback` is called with the [`Change`]s compared to the last
+    /// successful parse. If the parsing fails then the error is passed to the `callback` and the
+    /// last good variables are kept for the next comparison. Nothing is called if none of the
+    /// variables changed.
+    ///
+    /// Files are checked every `interval` in a background thread, which is stopped when the
+    /// returned [`Watcher`] is dropped. Use [`Change::apply`] to set the new values in the
+    /// process environment.
+    ///
+    /// Example
+    /// ```no_run
+    /// use std::{sync::mpsc, time::Duration};
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    ///
+    /// let _watcher = zenv::Zenv::new(".env", false)
+    ///     .watch(Duration::from_secs(1), move |changes| {
+    ///         tx.send(changes).ok();
+    ///     })
+    ///     .unwrap();
+    ///
+    /// for changes in rx {
+    ///     match changes {
+    ///         Ok(changes) => changes.iter().for_each(zenv::Change::apply),
+    ///         Err(e) => eprintln!("{}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn watch<F>(&self, interval: Duration, callback: F) -> Result<Watcher>
+    where
+        F: FnMut(Result<Vec<Change>>) + Send + 'static,
+    {
+        reload::watch(self.clone(), interval, callback)
+    }
+
+    /// Same as [Zenv::parse] but wraps the values into [`Value`]. Values of the keys which matches
+    /// the [secret patterns](Zenv::secrets) or are annotated with `# @secret` are masked in the
+    /// [`Debug`](std::fmt::Debug) and [`Display`](std::fmt::Display) output
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/.env.secret", false).parse_redacted().unwrap();
+    ///
+    /// assert_eq!(parsed["GITHUB_TOKEN"].to_string(), "***");
+    /// assert_eq!(parsed["GITHUB_TOKEN"].expose(), "ghp_token");
+    /// assert_eq!(parsed["PORT"].to_string(), "5000");
+    /// ```
+    pub fn parse_redacted(&self) -> Result<HashMap<String, Value>> {
+        let sources = self.read()?;
+        let lines = include::lines(&sources, self.keys, self.dialect, self.includes)?;
+        let (hash, _) = self.resolve(&lines)?;
+
+        Ok(lines
+            .redact(hash, &self.secrets)
+            .into_iter()
+            .map(|(k, v)| (self.filter.rename(k), v))
+            .collect())
+    }
+
+    /// Same as [Zenv::parse_redacted] but every value also carries its [`Origin`] i.e. the file,
+    /// line, raw text and the quote style where it is defined, the definitions it overrides and
+    /// the variables substituted into it
+    ///
+    /// Example
+    /// ```
+    /// let parsed = zenv::Zenv::new("tests/include/.env", true)
+    ///     .includes(true)
+    ///     .parse_origin()
+    ///     .unwrap();
+    ///
+    /// let (val, origin) = &parsed["EXPANDED"];
+    ///
+    /// assert_eq!(val.expose(), "common_local");
+    /// assert_eq!(origin.file, Some("tests/include/.env".into()));
+    /// assert_eq!(origin.line, 4);
+    /// assert_eq!(origin.refs, vec!["COMMON", "LOCAL"]);
+    /// ```
+    pub fn parse_origin(&self) -> Result<HashMap<String, (Value, Origin)>> {
+        let sources = self.read()?;
+        let lines = include::lines(&sources, self.keys, self.dialect, self.includes)?;
+        let (hash, mut refs) = self.resolve(&lines)?;
+        let hash = lines.redact(hash, &self.secrets);
+        let mut origins = lines.origins();
 
-        if !path.exists() {
-            return Err(Error::new(
-                ErrorKind::NotFound,
-                format!("Unable to find file - {}", path.display()),
-            ));
+        for (k, origin) in origins.iter_mut() {
+            if let Some(r) = refs.remove(k) {
+                origin.refs = r;
+            }
         }
 
-        let r = read_to_string(path)?;
-        let lines = Lines::from(r.as_str());
+        let parsed = hash
+            .into_iter()
+            .filter_map(|(k, v)| {
+                let origin = match origins.get(&k) {
+                    Some(x) => x.clone(),
+                    // Value is read from the file referenced by `KEY_FILE`
+                    None => {
+                        let file_key = format!("{}{}", k, files::FILE_SUFFIX);
+                        let mut x = origins.get(&file_key)?.clone();
+                        x.refs = vec![file_key];
+                        x
+                    }
+                };
 
-        let hash = match self.expand {
-            true => lines.expand(),
-            false => lines.to_hash_map(),
-        };
+                Some((self.filter.rename(k), (v, origin)))
+            })
+            .collect();
 
-        Ok(hash)
+        Ok(parsed)
     }
 
-    /// Parse the file using [Zenv::parse] and sets the environment variable
+    /// Returns the [`Origin`] of the variable, which is useful when the file includes other files
+    /// or the variable is defined multiple times. See [Zenv::parse_origin]
+    ///
+    /// Example
+    /// ```
+    /// let origin = zenv::Zenv::new("tests/include/.env", false)
+    ///     .includes(true)
+    ///     .origin("COMMON")
+    ///     .unwrap()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(origin.file, Some("tests/include/../.env.common".into()));
+    /// assert_eq!(origin.line, 1);
+    /// ```
+    pub fn origin(&self, key: &str) -> Result<Option<Origin>> {
+        Ok(self.parse_origin()?.remove(key).map(|(_, x)| x))
+    }
+
+    /// Returns the path of the env file along with the paths of all the files included by it, in
+    /// the order they are read. The env file itself is not included if the env data is not read
+    /// from a file e.g. [Zenv::from_str]
+    ///
+    /// Example
+    /// ```
+    /// let paths = zenv::Zenv::new("tests/include/.env", false)
+    ///     .includes(true)
+    ///     .paths()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(paths.len(), 3);
+    /// assert_eq!(paths[0], std::path::PathBuf::from("tests/include/.env"));
+    /// ```
+    pub fn paths(&self) -> Result<Vec<PathBuf>> {
+        let sources = self.read()?;
+
+        Ok(sources.into_iter().filter_map(|x| x.path).collect())
+    }
+
+    /// Same as [Zenv::configure] but the environment is restored when the returned guard is
+    /// dropped i.e. previous values are set again and the new variables are removed. Guards are
+    /// serialized using a global lock, so that parallel tests don't interleave. Guards can be
+    /// nested on the same thread, and are expected to be dropped in the reverse order
+    ///
+    /// Example
+    /// ```
+    /// {
+    ///     let _guard = zenv::Zenv::new("tests/.env.basic", false).scoped().unwrap();
+    ///
+    ///     assert_eq!(std::env::var_os("BASIC"), Some("basic".into()));
+    /// }
+    ///
+    /// assert_eq!(std::env::var_os("BASIC"), None);
+    /// ```
+    pub fn scoped(&self) -> Result<Scoped> {
+        Ok(Scoped::new(self.parse_ordered()?))
+    }
+
+    /// Parse the file using [Zenv::parse] and store the variables in a global snapshot, which is
+    /// read using [`var`] and [`vars`], instead of modifying the process environment. It is an
+    /// error if the snapshot is already initialized
+    ///
+    /// Example
+    /// ```
+    /// zenv::Zenv::new("tests/.env.basic", false).init().unwrap();
+    ///
+    /// assert_eq!(zenv::var("BASIC"), Ok("basic".to_string()));
+    /// assert!(zenv::Zenv::new("tests/.env.basic", false).init().is_err());
+    /// ```
+    pub fn init(&self) -> Result<()> {
+        snapshot::init(self.parse()?)
+    }
+
+    /// Parse the file using [Zenv::parse_os] and sets the environment variable. Prefer
+    /// [Zenv::init] in multi-threaded programs, as modifying the environment is racy
     ///
     /// Example
     /// ```
@@ -88,7 +822,7 @@ impl Zenv {
     /// assert_eq!(std::env::var_os("BASIC"), Some("basic".into()))
     /// ```
     pub fn configure(&self) -> Result<()> {
-        let vars = self.parse()?;
+        let vars = self.parse_os()?;
 
         for (key, val) in vars {
             std::env::set_var(key, val);
@@ -111,9 +845,21 @@ impl Zenv {
 ///
 /// // or with variable substitution
 /// zenv!(".env.development", true);
+///
+/// // or populate the snapshot, instead of the environment. See `zenv::var`
+/// zenv!(init, ".env.development", true);
 /// ````
 #[macro_export]
 macro_rules! zenv {
+    (init) => {
+        zenv::Zenv::new(".env", false).init().ok()
+    };
+    (init, $path:expr) => {
+        zenv::Zenv::new($path, false).init().ok()
+    };
+    (init, $path:expr, $expand:expr) => {
+        zenv::Zenv::new($path, $expand).init().ok()
+    };
     () => {
         zenv::Zenv::new(".env", false).configure().ok()
     };
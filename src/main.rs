@@ -1,35 +1,328 @@
 mod cli;
+mod compat;
+mod convert;
+mod explain;
 mod info;
+mod report;
+#[cfg(unix)]
+mod signal;
+mod watch;
 
-use cli::Cli;
-use std::process::{exit, Command, Stdio};
-use zenv::Zenv;
+use cli::{Cli, Subcommand};
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    io, mem,
+    process::{exit, Command, ExitStatus, Stdio},
+    time::Duration,
+};
+use zenv::{Dialect, Lines, Origin, Quote, Secrets, Value, Zenv, DEFAULT_PATTERNS};
 
-fn bootstrap() -> Result<i32, lexopt::Error> {
-    let cli = Cli::parse()?;
+// Secret patterns provided by the user are in addition to the default ones
+fn secrets(cli: &Cli) -> Vec<&str> {
+    let mut patterns = DEFAULT_PATTERNS.to_vec();
+    patterns.extend(cli.secrets.iter().map(String::as_str));
+    patterns
+}
+
+fn zenv(cli: &Cli) -> Result<Zenv, lexopt::Error> {
+    let zenv = match cli.path.to_str() {
+        Some("-") => Zenv::from_reader(io::stdin(), cli.expand).map_err(|e| e.to_string())?,
+        _ => Zenv::new(&cli.path, cli.expand),
+    };
+
+    let only: Vec<&str> = cli.only.iter().map(String::as_str).collect();
+    let exclude: Vec<&str> = cli.exclude.iter().map(String::as_str).collect();
+
+    let zenv = zenv
+        .commands(cli.commands)
+        .files(cli.files)
+        .file_keys(cli.file_keys)
+        .includes(cli.includes)
+        .utf16(cli.utf16)
+        .dialect(cli.dialect)
+        .strip_prefix(cli.strip_prefix)
+        .only(&only)
+        .exclude(&exclude)
+        .secrets(&secrets(cli));
+
+    let zenv = match &cli.prefix {
+        Some(prefix) => zenv.prefix(prefix),
+        None => zenv,
+    };
+
+    Ok(match cli.timeout {
+        Some(secs) => zenv.timeout(Duration::from_secs(secs)),
+        None => zenv,
+    })
+}
 
-    let cmd = cli.command.ok_or("<command> name is required")?;
+// Variables from `--env-string` followed by the ones from `-e`, which override the env file
+fn overrides(cli: &Cli) -> Vec<(String, String, Origin)> {
+    let mut vars = Vec::new();
+
+    if let Some(text) = &cli.env_string {
+        let lines = Lines::from(text.as_str());
+        let mut origins = lines.origins();
+
+        for (key, val) in lines.ordered(lines.to_hash_map()) {
+            if let Some(mut origin) = origins.remove(&key) {
+                origin.file = Some("--env-string".into());
+                for o in origin.overrides.iter_mut() {
+                    o.file = origin.file.clone();
+                }
+                vars.push((key, val, origin));
+            }
+        }
+    }
+
+    for (i, (key, val)) in cli.env.iter().enumerate() {
+        let origin = Origin {
+            file: Some("--env".into()),
+            line: i + 1,
+            raw: format!("{}={}", key, val),
+            quote: Quote::No,
+            overrides: vec![],
+            refs: vec![],
+        };
+        vars.push((key.to_string(), val.to_string(), origin));
+    }
+
+    vars
+}
+
+// Overrides the parsed variables, keeping track of the definitions they override
+fn merge(
+    parsed: &mut HashMap<String, (Value, Origin)>,
+    overrides: &[(String, String, Origin)],
+    secrets: &Secrets,
+) {
+    for (key, val, origin) in overrides {
+        let mut origin = origin.clone();
+
+        if let Some((_, mut old)) = parsed.remove(key) {
+            let mut earlier = mem::take(&mut old.overrides);
+            earlier.push(old);
+            earlier.append(&mut origin.overrides);
+            origin.overrides = earlier;
+        }
+
+        parsed.insert(
+            key.to_string(),
+            (secrets.wrap(key, val.to_string()), origin),
+        );
+    }
+}
 
-    let vars = Zenv::new(&cli.path, cli.expand)
-        .parse()
+fn explain(cli: &Cli, key: &str) -> Result<i32, lexopt::Error> {
+    if key.is_empty() {
+        return Err("<key> is required".into());
+    }
+
+    let mut parsed = zenv(cli)?
+        .lossy(true)
+        .parse_origin()
         .map_err(|e| e.to_string())?;
+    merge(&mut parsed, &overrides(cli), &Secrets::new(&secrets(cli)));
+
+    println!("{}", explain::explain(key, &parsed)?);
+
+    Ok(0)
+}
+
+fn compat_check(cli: &Cli) -> Result<i32, lexopt::Error> {
+    let zenv = zenv(cli)?.lossy(true);
+
+    let results: Vec<_> = Dialect::ALL
+        .iter()
+        .map(|d| {
+            let parsed = zenv.clone().dialect(*d).parse_redacted();
+            (*d, parsed.map_err(|e| e.to_string()))
+        })
+        .collect();
+
+    match compat::check(&results) {
+        Some(report) => {
+            println!("{}", report);
+            Ok(1)
+        }
+        None => {
+            println!("All the dialects agree");
+            Ok(0)
+        }
+    }
+}
+
+fn convert(cli: &Cli, to: Option<convert::Format>) -> Result<i32, lexopt::Error> {
+    let to = to.ok_or("--to <format> is required")?;
+    let mut vars = zenv(cli)?.parse_ordered().map_err(|e| e.to_string())?;
 
-    let mut program = Command::new(&cmd)
+    for (key, val, _) in overrides(cli) {
+        match vars.iter_mut().find(|(k, _)| *k == key) {
+            Some(var) => var.1 = val,
+            None => vars.push((key, val)),
+        }
+    }
+
+    print!("{}", convert::convert(&vars, to)?);
+
+    Ok(0)
+}
+
+fn command(cli: &Cli, cmd: &OsStr, vars: HashMap<OsString, OsString>) -> Command {
+    let mut command = Command::new(cmd);
+
+    if cli.clear {
+        command.env_clear();
+
+        for key in &cli.keep {
+            if let Some(val) = env::var_os(key) {
+                command.env(key, val);
+            }
+        }
+    }
+
+    command
         .args(&cli.args)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
-        .envs(vars)
-        .spawn()
-        .map_err(|_| "Unable to spawn program!")?;
+        .envs(vars);
 
-    let code = program
-        .wait()
-        .map_err(|e| e.to_string())?
-        .code()
-        .ok_or("Failed to grab the exit code!")?;
+    command
+}
+
+fn run(cli: Cli) -> Result<i32, lexopt::Error> {
+    let cmd = cli.command.as_deref().ok_or("<command> name is required")?;
+
+    if cli.watch {
+        if cli.exec {
+            return Err("--watch can't be used with --exec".into());
+        }
+
+        // Checked before reading stdin, which has no file to watch
+        if cli.path.to_str() == Some("-") {
+            return Err("--watch can't be used with -f -".into());
+        }
+    }
+
+    let zenv = zenv(&cli)?;
+    let overrides = overrides(&cli);
+
+    // Variables of the report, if they are exactly the ones which the command gets
+    let mut reported = None;
+
+    if cli.verbose || cli.dry_run {
+        // Lossy decoding only changes the files which aren't valid UTF-8
+        let exact = zenv.paths().is_ok();
+
+        // Reports are only for humans, so the invalid UTF-8 doesn't matter. Commands are only run
+        // once, for the report if it is reused for the command, and never for a dry run
+        let zenv = zenv
+            .clone()
+            .lossy(true)
+            .commands(cli.commands && exact && !cli.dry_run && !cli.watch);
+
+        let paths = zenv.paths().map_err(|e| e.to_string())?;
+        let mut parsed = zenv.parse_origin().map_err(|e| e.to_string())?;
+        let secrets = Secrets::new(&secrets(&cli));
+        merge(&mut parsed, &overrides, &secrets);
+        let keep = Some(cli.keep.as_slice()).filter(|_| cli.clear);
+
+        if cli.verbose {
+            report::verbose(&paths, &parsed, &secrets, keep);
+        }
+
+        if cli.dry_run {
+            report::dry_run(cmd, &cli.args, &paths, &parsed, &secrets, keep);
+            return Ok(0);
+        }
+
+        if exact {
+            reported = Some(parsed);
+        }
+    }
+
+    if cli.watch {
+        let grace = Duration::from_secs(cli.grace.unwrap_or(5));
+        let code = watch::watch(&zenv, grace, |mut vars| {
+            vars.extend(overrides.iter().map(|(k, v, _)| (k.into(), v.into())));
+            command(&cli, cmd, vars)
+        })?;
+
+        return Ok(code);
+    }
+
+    let vars = match reported {
+        Some(parsed) => parsed
+            .into_iter()
+            .map(|(k, (v, _))| (k.into(), v.expose().into()))
+            .collect(),
+        None => {
+            // Env files can have arbitrary bytes, same as the environment
+            let mut vars = zenv.parse_os().map_err(|e| e.to_string())?;
+            vars.extend(overrides.into_iter().map(|(k, v, _)| (k.into(), v.into())));
+            vars
+        }
+    };
+
+    let mut command = command(&cli, cmd, vars);
+
+    if cli.exec {
+        return exec(command);
+    }
+
+    let mut program = command.spawn().map_err(|_| "Unable to spawn program!")?;
+
+    #[cfg(unix)]
+    signal::forward(program.id()).map_err(|e| e.to_string())?;
+
+    let status = program.wait().map_err(|e| e.to_string())?;
+
+    Ok(exit_code(status))
+}
+
+// Replaces the zenv process with the command, so the PID, signals and exit code are the same
+// as if the command was executed directly
+#[cfg(unix)]
+fn exec(mut command: Command) -> Result<i32, lexopt::Error> {
+    use std::os::unix::process::CommandExt;
+
+    // Only returns if the exec failed
+    let e = command.exec();
+
+    Err(format!("Unable to exec program! {}", e).into())
+}
+
+#[cfg(not(unix))]
+fn exec(_: Command) -> Result<i32, lexopt::Error> {
+    Err("--exec is only supported on unix".into())
+}
+
+// Same as shells, if the command is killed by a signal then the exit code is 128 + signal
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(sig) = status.signal() {
+            return 128 + sig;
+        }
+    }
+
+    status.code().unwrap_or(1)
+}
+
+fn bootstrap() -> Result<i32, lexopt::Error> {
+    let cli = Cli::parse()?;
 
-    Ok(code)
+    match &cli.subcommand {
+        Some(Subcommand::Explain(key)) => explain(&cli, key),
+        Some(Subcommand::CompatCheck) => compat_check(&cli),
+        Some(Subcommand::Convert(to)) => convert(&cli, *to),
+        None => run(cli),
+    }
 }
 
 fn main() {
@@ -0,0 +1,471 @@
+use std::{io::Result, path::Path};
+
+use zenv_core::{KeyVal, Line, Quote};
+
+use super::dialect::{column, error, push, Parsed, Part, Problem};
+
+/// Parse the text using the shell word rules, a statement can span multiple lines if a quote is
+/// not closed or a line ends with `\`. Each assignment becomes a [`Line::KeyVal`] and the raw text
+/// is the whole statement
+pub(crate) fn lines<'l>(text: &'l str, file: Option<&Path>) -> Result<Vec<Parsed<'l>>> {
+    let mut lines = Vec::new();
+
+    for parsed in Parser::new(text) {
+        let (n, raw, statement) = parsed.map_err(|x| error(file, x))?;
+
+        match statement {
+            Statement::Assign(assigns) => {
+                for Assign {
+                    key,
+                    col,
+                    parts,
+                    quote,
+                } in assigns
+                {
+                    let kv = KeyVal {
+                        k: key,
+                        v: parts.iter().map(|x| x.0.as_str()).collect(),
+                        q: quote,
+                    };
+
+                    lines.push((n, col, raw, Line::KeyVal(kv), Some(parts)));
+                }
+            }
+            Statement::Include(path) => lines.push((n, 0, raw, Line::Include(path), None)),
+            Statement::Empty => lines.push((n, 0, raw, Line::Empty, None)),
+        }
+    }
+
+    Ok(lines)
+}
+
+struct Assign<'l> {
+    key: &'l str,
+    // Column of the key in its line
+    col: usize,
+    parts: Vec<Part>,
+    quote: Quote,
+}
+
+enum Statement<'l> {
+    // One or more assignments e.g. `export A=1 B=2`
+    Assign(Vec<Assign<'l>>),
+    // `source <path>` or `. <path>`
+    Include(&'l str),
+    // Comment, blank line or a command which doesn't change the variables i.e. `set -a`
+    Empty,
+}
+
+struct Parser<'l> {
+    text: &'l str,
+    pos: usize,
+    // Line number at `pos`, starting from 1
+    line: usize,
+}
+
+impl<'l> Iterator for Parser<'l> {
+    type Item = std::result::Result<(usize, &'l str, Statement<'l>), Problem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.blanks();
+        self.peek()?;
+
+        let text = self.text;
+        let (start, n) = (self.pos, self.line);
+        let statement = self.statement(start);
+        let raw = text[start..self.pos].trim_end();
+
+        // Newline or `;` which ends the statement
+        self.bump();
+
+        Some(statement.map(|x| (n, raw, x)))
+    }
+}
+
+impl<'l> Parser<'l> {
+    fn new(text: &'l str) -> Self {
+        Self {
+            text: text.strip_prefix('\u{feff}').unwrap_or(text),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+        }
+
+        Some(c)
+    }
+
+    /// Consume the `\r` if it is a part of CRLF
+    fn crlf(&mut self) -> bool {
+        if self.text[self.pos..].starts_with("\r\n") {
+            self.bump();
+            return true;
+        }
+
+        false
+    }
+
+    /// Consume the line continuation i.e. `\` at the end of a line
+    fn continuation(&mut self) -> bool {
+        let rest = &self.text[self.pos..];
+
+        if rest.starts_with("\\\n") || rest.starts_with("\\\r\n") {
+            self.bump();
+            self.crlf();
+            self.bump();
+            return true;
+        }
+
+        false
+    }
+
+    fn blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ') | Some('\t') => {
+                    self.bump();
+                }
+                Some('\r') if self.crlf() => {}
+                Some('\\') if self.continuation() => {}
+                _ => break,
+            }
+        }
+    }
+
+    /// Comment until the end of the line, without the newline
+    fn comment(&mut self) {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.bump();
+        }
+    }
+
+    /// Key of an assignment, along with the `=`, if the next word is one
+    fn key(&mut self) -> Option<&'l str> {
+        let text = self.text;
+        let rest = &text[self.pos..];
+        let len = rest.find(|c: char| c.is_whitespace() || "=\"'\\$;#&|<>()`".contains(c))?;
+
+        if len == 0 || !rest[len..].starts_with('=') {
+            return None;
+        }
+
+        self.pos += len + 1;
+
+        Some(&rest[..len])
+    }
+
+    fn statement(&mut self, start: usize) -> std::result::Result<Statement<'l>, Problem> {
+        let mut assigns = Vec::new();
+        let mut export = false;
+
+        loop {
+            self.blanks();
+
+            match self.peek() {
+                None | Some('\n') | Some(';') => break,
+                Some('#') => {
+                    self.comment();
+                    break;
+                }
+                _ => {}
+            }
+
+            let col = column(self.text, self.pos);
+
+            if let Some(key) = self.key() {
+                let (parts, quote) = self.word()?;
+                assigns.push(Assign {
+                    key,
+                    col,
+                    parts,
+                    quote,
+                });
+                continue;
+            }
+
+            let n = self.line;
+            let (parts, _) = self.word()?;
+            let word: String = parts.iter().map(|x| x.0.as_str()).collect();
+
+            // `export KEY` doesn't change the value
+            if export {
+                continue;
+            }
+
+            if assigns.is_empty() {
+                match word.as_str() {
+                    "export" => {
+                        export = true;
+                        continue;
+                    }
+                    "set" => {
+                        self.rest()?;
+                        return Ok(Statement::Empty);
+                    }
+                    "source" | "." => {
+                        self.rest()?;
+                        let text = self.text;
+
+                        return Line::include(&text[start..self.pos])
+                            .map(Statement::Include)
+                            .ok_or_else(|| (n, format!("Missing path after `{}`", word)));
+                    }
+                    _ => {}
+                }
+            }
+
+            return Err((
+                n,
+                format!(
+                    "Unsupported command `{}`, expected KEY=VALUE, export, set or source",
+                    word
+                ),
+            ));
+        }
+
+        Ok(match assigns.is_empty() {
+            true => Statement::Empty,
+            false => Statement::Assign(assigns),
+        })
+    }
+
+    /// Skip the remaining words of the statement
+    fn rest(&mut self) -> std::result::Result<(), Problem> {
+        loop {
+            self.blanks();
+
+            match self.peek() {
+                None | Some('\n') | Some(';') => return Ok(()),
+                Some('#') => {
+                    self.comment();
+                    return Ok(());
+                }
+                _ => {
+                    self.word()?;
+                }
+            }
+        }
+    }
+
+    /// Parse a word into parts, along with the style of the first quote in it
+    fn word(&mut self) -> std::result::Result<(Vec<Part>, Quote), Problem> {
+        let mut parts = Vec::new();
+        // Unquoted parts, which are kept separate from the quoted ones so that `"$A"B` doesn't
+        // become `$AB`
+        let mut run = Vec::new();
+        let mut quote = Quote::No;
+
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | ';' => break,
+                '\r' if self.crlf() => break,
+                '&' | '|' | '<' | '>' | '(' | ')' | '`' => {
+                    return Err((
+                        self.line,
+                        format!("Unsupported `{}`, quote the value to use it as it is", c),
+                    ));
+                }
+                '\\' => {
+                    if !self.continuation() {
+                        self.bump();
+
+                        match self.bump() {
+                            Some(c) => push(&mut run, c.encode_utf8(&mut [0; 4]), false),
+                            None => push(&mut run, "\\", false),
+                        }
+                    }
+                }
+                '\'' => {
+                    self.bump();
+
+                    if quote == Quote::No {
+                        quote = Quote::Single;
+                    }
+
+                    parts.append(&mut run);
+                    self.single(&mut parts)?;
+                }
+                '"' => {
+                    self.bump();
+
+                    if quote == Quote::No {
+                        quote = Quote::Double;
+                    }
+
+                    let mut quoted = Vec::new();
+                    self.double(&mut quoted)?;
+
+                    parts.append(&mut run);
+                    parts.append(&mut quoted);
+                }
+                '$' => {
+                    self.bump();
+
+                    match self.peek() {
+                        // ANSI-C quoting i.e. `$'a\nb'`
+                        Some('\'') => {
+                            self.bump();
+
+                            if quote == Quote::No {
+                                quote = Quote::Single;
+                            }
+
+                            parts.append(&mut run);
+                            self.ansi(&mut parts)?;
+                        }
+                        // Same as the double quotes, handled in the next iteration
+                        Some('"') => {}
+                        _ => self.dollar(&mut run)?,
+                    }
+                }
+                _ => {
+                    self.bump();
+                    push(&mut run, c.encode_utf8(&mut [0; 4]), true);
+                }
+            }
+        }
+
+        parts.append(&mut run);
+
+        Ok((parts, quote))
+    }
+
+    /// Single quoted text, after the opening quote, where nothing is special
+    fn single(&mut self, parts: &mut Vec<Part>) -> std::result::Result<(), Problem> {
+        let n = self.line;
+        let mut text = String::new();
+
+        loop {
+            match self.bump() {
+                None => return Err((n, "Unclosed `'`".to_string())),
+                Some('\'') => break,
+                Some('\r') if self.peek() == Some('\n') => {}
+                Some(c) => text.push(c),
+            }
+        }
+
+        push(parts, &text, false);
+
+        Ok(())
+    }
+
+    /// Double quoted text, after the opening quote, where `\` only escapes `$`, `` ` ``, `"`, `\`
+    /// and the newline
+    fn double(&mut self, parts: &mut Vec<Part>) -> std::result::Result<(), Problem> {
+        let n = self.line;
+
+        loop {
+            match self.bump() {
+                None => return Err((n, "Unclosed `\"`".to_string())),
+                Some('"') => return Ok(()),
+                Some('\r') if self.peek() == Some('\n') => {}
+                Some('\\') => match self.peek() {
+                    Some('\n') => {
+                        self.bump();
+                    }
+                    Some(c) if "$`\"\\".contains(c) => {
+                        self.bump();
+                        push(parts, c.encode_utf8(&mut [0; 4]), false);
+                    }
+                    _ => push(parts, "\\", true),
+                },
+                Some('$') => self.dollar(parts)?,
+                Some(c) => push(parts, c.encode_utf8(&mut [0; 4]), true),
+            }
+        }
+    }
+
+    /// ANSI-C quoted text i.e. `$'...'`, after the opening quote, where the escapes are replaced
+    fn ansi(&mut self, parts: &mut Vec<Part>) -> std::result::Result<(), Problem> {
+        let n = self.line;
+        let mut text = String::new();
+
+        loop {
+            match self.bump() {
+                None => return Err((n, "Unclosed `$'`".to_string())),
+                Some('\'') => break,
+                Some('\\') => match self.bump() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some('e') | Some('E') => text.push('\x1b'),
+                    Some(c) if "\\'\"?".contains(c) => text.push(c),
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => return Err((n, "Unclosed `$'`".to_string())),
+                },
+                Some(c) => text.push(c),
+            }
+        }
+
+        push(parts, &text, false);
+
+        Ok(())
+    }
+
+    /// Text after a `$`, which is expanded only if it is followed by a name, `{` or `(`. References
+    /// are kept as separate parts, as they are written, so that `$A$B` is not read as a single name
+    fn dollar(&mut self, parts: &mut Vec<Part>) -> std::result::Result<(), Problem> {
+        let text = self.text;
+        let start = self.pos;
+
+        let (open, close) = match self.peek() {
+            Some('{') => ('{', '}'),
+            Some('(') => ('(', ')'),
+            Some(c) if c.is_ascii_alphanumeric() || c == '_' => {
+                // Positional parameters only take a single digit i.e. `$12` is `${1}2`
+                match c.is_ascii_digit() {
+                    true => {
+                        self.bump();
+                    }
+                    false => {
+                        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_')
+                        {
+                            self.bump();
+                        }
+                    }
+                }
+
+                parts.push((format!("${}", &text[start..self.pos]), true));
+                return Ok(());
+            }
+            _ => {
+                push(parts, "$", false);
+                return Ok(());
+            }
+        };
+
+        let n = self.line;
+        let mut depth = 0;
+
+        // Only counting the brackets, same as the command substitution does
+        self.bump();
+
+        loop {
+            match self.bump() {
+                None => return Err((n, format!("Unclosed `${}`", open))),
+                Some(c) if c == close && depth == 0 => break,
+                Some(c) if c == close => depth -= 1,
+                Some(c) if c == open => depth += 1,
+                _ => {}
+            }
+        }
+
+        parts.push((format!("${}", &text[start..self.pos]), true));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Read, Result},
+    process::{Command, Stdio},
+    str::Chars,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the running command is checked for completion
+const POLL: Duration = Duration::from_millis(10);
+
+/// Collect the command until the matching `)`, assuming the `$(` is already consumed
+pub(crate) fn take(chars: &mut Chars) -> String {
+    let mut depth = 0;
+    let mut cmd = String::new();
+
+    for c in chars {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        cmd.push(c);
+    }
+
+    cmd
+}
+
+fn shell(cmd: &str) -> Command {
+    if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", cmd]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.args(["-c", cmd]);
+        c
+    }
+}
+
+fn drain<R: Read + Send + 'static>(mut r: R) -> thread::JoinHandle<Vec<u8>> {
+    // Reading in a separate thread so that the command doesn't block on a full pipe
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf).ok();
+        buf
+    })
+}
+
+/// Run the command in a shell with the provided variables and return the trimmed stdout
+pub(crate) fn run(cmd: &str, vars: &HashMap<String, String>, timeout: Duration) -> Result<String> {
+    let mut child = shell(cmd)
+        .envs(vars)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| error(&e, format!("Unable to run command `{}` - {}", cmd, e)))?;
+
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let start = Instant::now();
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if start.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                format!("Command `{}` timed out after {:?}", cmd, timeout),
+            ));
+        }
+
+        thread::sleep(POLL);
+    };
+
+    let collect = |h: Option<thread::JoinHandle<Vec<u8>>>| {
+        h.and_then(|h| h.join().ok())
+            .map(|x| String::from_utf8_lossy(&x).trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let out = collect(stdout);
+
+    if !status.success() {
+        let err = collect(stderr);
+
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!("Command `{}` failed with {} - {}", cmd, status, err),
+        ));
+    }
+
+    Ok(out)
+}
+
+/// Create a new error with same kind but with different message
+pub(crate) fn error(e: &Error, msg: String) -> Error {
+    Error::new(e.kind(), msg)
+}
@@ -0,0 +1,166 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    str::FromStr,
+};
+
+use zenv_core::Line;
+
+use super::{bash, docker, dotenv};
+
+/// Syntax of the env files, as the tools which read them don't agree on the details. Dialects of
+/// the other tools follow their documented behavior, which is good enough to find the values
+/// they would read differently
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    /// Syntax described in the README, which is line based
+    Zenv,
+
+    /// Shell word rules, so that the file means the same as it does with `source .env` in bash.
+    /// Quoted values can span multiple lines, unquoted values end at the first whitespace and the
+    /// variables are expanded in the order they are defined
+    Bash,
+
+    /// Node's `dotenv`, where `#` always starts a comment in an unquoted value, only `\n` and
+    /// `\r` are escaped in double quotes, backticks also quote and nothing is expanded
+    Node,
+
+    /// Python's `python-dotenv`, where `#` needs a whitespace before it to start a comment,
+    /// double quotes support the escape sequences, and only `${NAME}` is expanded
+    Python,
+
+    /// Ruby's `dotenv`, where `#` always starts a comment in an unquoted value, `\` escapes any
+    /// character outside single quotes, and `$NAME`, `${NAME}` and `$(command)` are expanded
+    Ruby,
+
+    /// Docker compose's `env_file`, where `#` needs a whitespace before it to start a comment,
+    /// `$NAME` and `${NAME}` are expanded, `$$` is a `$` and unclosed quotes are an error
+    Compose,
+
+    /// Docker's `--env-file`, where the value is the text after the `=` as it is, quotes
+    /// included, and nothing is expanded
+    Docker,
+}
+
+impl Dialect {
+    /// All the dialects, in the order they are listed
+    pub const ALL: &'static [Dialect] = &[
+        Self::Zenv,
+        Self::Bash,
+        Self::Node,
+        Self::Python,
+        Self::Ruby,
+        Self::Compose,
+        Self::Docker,
+    ];
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::Zenv
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Zenv => "zenv",
+            Self::Bash => "bash",
+            Self::Node => "node",
+            Self::Python => "python",
+            Self::Ruby => "ruby",
+            Self::Compose => "compose",
+            Self::Docker => "docker",
+        })
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|x| x.to_string() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<String> = Self::ALL.iter().map(Self::to_string).collect();
+                format!("Unknown dialect `{}`, expected {}", s, names.join(", "))
+            })
+    }
+}
+
+impl Dialect {
+    /// Parse a single line according to the dialect, same as [`Line::from`] does for the
+    /// [`Dialect::Zenv`]. Values which span multiple lines need
+    /// [`Lines::parse_with`](crate::Lines::parse_with)
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{Dialect, KeyVal, Line, Quote};
+    ///
+    /// let line = Dialect::Bash.parse_line("URL=localhost#main").unwrap();
+    ///
+    /// let k = "URL";
+    /// let v = "localhost#main".to_string();
+    /// assert_eq!(line, Line::KeyVal(KeyVal { k, v, q: Quote::No }));
+    ///
+    /// let line = Dialect::Node.parse_line("URL=localhost#main").unwrap();
+    ///
+    /// let v = "localhost".to_string();
+    /// assert_eq!(line, Line::KeyVal(KeyVal { k, v, q: Quote::No }));
+    /// ```
+    pub fn parse_line(self, line: &str) -> Result<Line<'_>> {
+        let parsed = match self {
+            Self::Zenv => return Ok(Line::from(line)),
+            Self::Bash => bash::lines(line, None)?,
+            Self::Docker => docker::lines(line, None)?,
+            _ => dotenv::lines(line, None, self)?,
+        };
+
+        Ok(parsed
+            .into_iter()
+            .map(|(_, _, _, line, _)| line)
+            .next()
+            .unwrap_or(Line::Empty))
+    }
+}
+
+/// Part of a value along with whether it is expanded, single quoted and escaped parts are not
+pub(crate) type Part = (String, bool);
+
+/// Parsed line along with its line number, the column of the key (0 if there is no key), text and
+/// the parts of the value
+pub(crate) type Parsed<'l> = (usize, usize, &'l str, Line<'l>, Option<Vec<Part>>);
+
+/// Message along with the line number where the problem is
+pub(crate) type Problem = (usize, String);
+
+/// Column, starting from 1, of the byte at `pos` in its line
+pub(crate) fn column(text: &str, pos: usize) -> usize {
+    let start = text[..pos].rfind('\n').map_or(0, |i| i + 1);
+
+    text[start..pos].chars().count() + 1
+}
+
+/// Error which points to the line of the [`Problem`]
+pub(crate) fn error(file: Option<&Path>, (n, msg): Problem) -> Error {
+    let position = match file {
+        Some(file) => format!("{}:{}", file.display(), n),
+        None => format!("Line {}", n),
+    };
+
+    Error::new(ErrorKind::InvalidData, format!("{}: {}", position, msg))
+}
+
+/// Append to the last part if it is of the same kind. References i.e. the expanded parts which
+/// start with `$` are never appended to, as the text after `$A` is not a part of the name
+pub(crate) fn push(parts: &mut Vec<Part>, text: &str, expand: bool) {
+    match parts.last_mut() {
+        Some(last) if last.1 == expand && !(expand && last.0.starts_with('$')) => {
+            last.0.push_str(text)
+        }
+        _ => parts.push((text.to_string(), expand)),
+    }
+}
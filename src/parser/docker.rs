@@ -0,0 +1,58 @@
+use std::{env, io::Result, path::Path};
+
+use zenv_core::{KeyVal, Line, Quote};
+
+use super::dialect::{column, error, Parsed};
+
+/// Parse the text like `docker run --env-file` does. The value is everything after the first `=`,
+/// as it is, so the quotes are a part of it and nothing is expanded. A line with only the key takes
+/// the value from the environment, and is skipped if the variable is not set
+pub(crate) fn lines<'l>(text: &'l str, file: Option<&Path>) -> Result<Vec<Parsed<'l>>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut lines = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim_start();
+
+        if line.is_empty() || line.starts_with('#') {
+            lines.push((i + 1, 0, raw, Line::Empty, None));
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((k, v)) => (k, Some(v.to_string())),
+            None => (line, env::var(line).ok()),
+        };
+
+        if key.is_empty() {
+            let msg = format!("No variable name in `{}`", line);
+            return Err(error(file, (i + 1, msg)));
+        }
+
+        if key.contains(&[' ', '\t'][..]) {
+            let msg = format!("Variable `{}` contains whitespaces", key);
+            return Err(error(file, (i + 1, msg)));
+        }
+
+        let parsed = match value {
+            Some(v) => {
+                let parts = vec![(v.clone(), false)];
+                let kv = KeyVal {
+                    k: key,
+                    v,
+                    q: Quote::No,
+                };
+
+                // Key is always a suffix of the line, after the indentation
+                let col = column(raw, raw.len() - line.len());
+
+                (i + 1, col, raw, Line::KeyVal(kv), Some(parts))
+            }
+            None => (i + 1, 0, raw, Line::Empty, None),
+        };
+
+        lines.push(parsed);
+    }
+
+    Ok(lines)
+}
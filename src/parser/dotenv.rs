@@ -0,0 +1,356 @@
+use std::{io::Result, path::Path};
+
+use zenv_core::{KeyVal, Line, Quote};
+
+use super::dialect::{column, error, push, Dialect, Parsed, Part, Problem};
+
+/// What happens to a quoted value which is not closed, or has text after the closing quote
+#[derive(Clone, Copy, PartialEq)]
+enum Invalid {
+    /// Value is read as an unquoted one
+    Unquoted,
+    /// Line is skipped
+    Skip,
+    /// It is an error
+    Error,
+}
+
+/// Documented behavior of a dotenv implementation, as they differ in the details
+struct Rules {
+    /// Characters which can quote a value, backticks are same as the single quotes
+    quotes: &'static str,
+    /// Whether `KEY: value` is the same as `KEY=value`
+    colon: bool,
+    /// Whether `#` needs a whitespace before it to start a comment in an unquoted value
+    spaced_comment: bool,
+    invalid: Invalid,
+    /// Whether the unquoted and double quoted values are expanded
+    expand: bool,
+    /// Whether only `${NAME}` is expanded, and not `$NAME`
+    braces: bool,
+    /// Whether `${NAME:-default}` is supported
+    defaults: bool,
+    /// Whether `$(command)` is substituted
+    commands: bool,
+    /// Characters allowed in a key
+    key: fn(char) -> bool,
+}
+
+fn word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '-'
+}
+
+impl Rules {
+    fn of(dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::Node => Self {
+                quotes: "'\"`",
+                colon: true,
+                spaced_comment: false,
+                invalid: Invalid::Unquoted,
+                expand: false,
+                braces: false,
+                defaults: false,
+                commands: false,
+                key: word,
+            },
+            Dialect::Python => Self {
+                quotes: "'\"",
+                colon: false,
+                spaced_comment: true,
+                invalid: Invalid::Skip,
+                expand: true,
+                braces: true,
+                defaults: true,
+                commands: false,
+                key: |c| !c.is_whitespace() && c != '=' && c != '#',
+            },
+            Dialect::Ruby => Self {
+                quotes: "'\"",
+                colon: true,
+                spaced_comment: false,
+                invalid: Invalid::Unquoted,
+                expand: true,
+                braces: false,
+                defaults: false,
+                commands: true,
+                key: |c| c.is_alphanumeric() || c == '_' || c == '.',
+            },
+            // Compose, the other dialects are not parsed here
+            _ => Self {
+                quotes: "'\"",
+                colon: false,
+                spaced_comment: true,
+                invalid: Invalid::Error,
+                expand: true,
+                braces: false,
+                defaults: true,
+                commands: false,
+                key: word,
+            },
+        }
+    }
+}
+
+/// Parse the text like the dotenv implementation of the `dialect` does. A quoted value can span
+/// multiple lines, in which case the raw text contains all of them
+pub(crate) fn lines<'l>(
+    text: &'l str,
+    file: Option<&Path>,
+    dialect: Dialect,
+) -> Result<Vec<Parsed<'l>>> {
+    let rules = Rules::of(dialect);
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut lines = Vec::new();
+    let (mut pos, mut n) = (0, 1);
+
+    while pos < text.len() {
+        let (line, col, parts, end) =
+            statement(text, pos, n, &rules, dialect).map_err(|x| error(file, x))?;
+
+        lines.push((n, col, text[pos..end].trim(), line, parts));
+
+        n += text[pos..end].matches('\n').count() + 1;
+        pos = end + 1;
+    }
+
+    Ok(lines)
+}
+
+/// End of the line which contains `pos`, without the line break
+fn eol(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i)
+}
+
+fn trim_cr(line: &str) -> &str {
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Parsed statement along with the column of the key and the end of its last line
+type Statement<'l> = (Line<'l>, usize, Option<Vec<Part>>, usize);
+
+/// Parse the statement which starts at `start`
+fn statement<'l>(
+    text: &'l str,
+    start: usize,
+    n: usize,
+    rules: &Rules,
+    dialect: Dialect,
+) -> std::result::Result<Statement<'l>, Problem> {
+    let end = eol(text, start);
+    let line = trim_cr(&text[start..end]);
+    let ignored = Ok((Line::Empty, 0, None, end));
+
+    let body = line.trim_start();
+
+    if body.is_empty() || body.starts_with('#') {
+        return ignored;
+    }
+
+    let body = match body.strip_prefix("export") {
+        Some(x) if x.starts_with(|c| c == ' ' || c == '\t') => x.trim_start(),
+        _ => body,
+    };
+
+    let (key, rest) = body.split_at(body.find(|c| !(rules.key)(c)).unwrap_or(body.len()));
+    let rest = rest.trim_start_matches(&[' ', '\t'][..]);
+
+    let value = match rest.strip_prefix('=') {
+        Some(x) => x,
+        None if rules.colon && (rest.starts_with(": ") || rest.starts_with(":\t")) => &rest[1..],
+        // Lines which are not assignments are ignored, even `KEY` without a value
+        None => return ignored,
+    };
+
+    if key.is_empty() {
+        return ignored;
+    }
+
+    // Key is always a suffix of the line, after the indentation and `export`
+    let col = column(text, start + line.len() - body.len());
+
+    let value = value.trim_start_matches(&[' ', '\t'][..]);
+    // Value is always a suffix of the line
+    let at = start + line.len() - value.len();
+
+    if let Some(q) = value.chars().next().filter(|x| rules.quotes.contains(*x)) {
+        if let Some(close) = closing(text, at + 1, q) {
+            let end = eol(text, close);
+            let after = trim_cr(&text[close + 1..end]).trim_start();
+
+            if after.is_empty() || after.starts_with('#') {
+                let quote = match q {
+                    '"' => Quote::Double,
+                    _ => Quote::Single,
+                };
+
+                let content = text[at + 1..close].replace("\r\n", "\n");
+                let parts = parts(&content, quote, rules, dialect);
+
+                return Ok((kv(key, &parts, quote), col, Some(parts), end));
+            }
+        }
+
+        match rules.invalid {
+            Invalid::Unquoted => {}
+            Invalid::Skip => return ignored,
+            Invalid::Error => {
+                return Err((
+                    n,
+                    format!(
+                        "Unclosed `{}`, or text after the closing quote, in `{}`",
+                        q, key
+                    ),
+                ))
+            }
+        }
+    }
+
+    let comment = match rules.spaced_comment {
+        true => value
+            .char_indices()
+            .zip(value.chars().skip(1))
+            .find(|((_, c), next)| c.is_whitespace() && *next == '#')
+            .map(|((i, _), _)| i),
+        false => value.find('#'),
+    };
+
+    let value = value[..comment.unwrap_or(value.len())].trim();
+    let parts = parts(value, Quote::No, rules, dialect);
+
+    Ok((kv(key, &parts, Quote::No), col, Some(parts), end))
+}
+
+fn kv<'l>(key: &'l str, parts: &[Part], quote: Quote) -> Line<'l> {
+    Line::KeyVal(KeyVal {
+        k: key,
+        v: parts.iter().map(|x| x.0.as_str()).collect(),
+        q: quote,
+    })
+}
+
+/// Position of the closing quote, a quote preceded by `\` doesn't close the value
+fn closing(text: &str, from: usize, q: char) -> Option<usize> {
+    let mut chars = text[from..].char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == q => return Some(from + i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Character which replaces the escape sequence i.e. `\` followed by `c`, if it is one
+fn escape(dialect: Dialect, quote: Quote, c: char) -> Option<char> {
+    match (dialect, quote, c) {
+        (Dialect::Node, Quote::Double, 'n') => Some('\n'),
+        (Dialect::Node, Quote::Double, 'r') => Some('\r'),
+        (Dialect::Python, Quote::Single, '\\') | (Dialect::Python, Quote::Single, '\'') => Some(c),
+        (Dialect::Python, Quote::Double, _) => match c {
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'f' => Some('\x0c'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\x0b'),
+            '\\' | '\'' | '"' => Some(c),
+            _ => None,
+        },
+        (Dialect::Ruby, Quote::Double, 'n') | (Dialect::Compose, Quote::Double, 'n') => Some('\n'),
+        (Dialect::Ruby, Quote::Double, 'r') | (Dialect::Compose, Quote::Double, 'r') => Some('\r'),
+        (Dialect::Compose, Quote::Double, 't') => Some('\t'),
+        (Dialect::Ruby, Quote::Double, _)
+        | (Dialect::Ruby, Quote::No, _)
+        | (Dialect::Compose, Quote::Double, _) => Some(c),
+        _ => None,
+    }
+}
+
+/// Length of the reference after a `$`, if it is one, which is understood by the expansion i.e.
+/// `$NAME`, `${NAME}` or `$(command)`
+fn reference(rest: &str, rules: &Rules) -> Option<usize> {
+    let name = |x: &str| {
+        x.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(x.len())
+    };
+
+    let (open, close) = match rest.chars().next()? {
+        // Only the name is the reference, and the closing brace is optional
+        '{' if !rules.defaults => {
+            let len = name(&rest[1..]);
+            let closed = rest[1 + len..].starts_with('}');
+
+            return Some(1 + len + closed as usize);
+        }
+        '{' => ('{', '}'),
+        '(' if rules.commands => ('(', ')'),
+        _ if rules.braces => return None,
+        _ => {
+            let len = name(rest);
+
+            return Some(len).filter(|x| *x > 0);
+        }
+    };
+
+    let mut depth = 0;
+
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            c if c == close && depth == 0 => return Some(i + 1),
+            c if c == close => depth -= 1,
+            c if c == open => depth += 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Split the value into parts after replacing the escape sequences
+fn parts(value: &str, quote: Quote, rules: &Rules, dialect: Dialect) -> Vec<Part> {
+    let expand = rules.expand && quote != Quote::Single;
+    let mut parts = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = value[i..].chars().next() {
+        i += c.len_utf8();
+
+        match c {
+            '\\' => {
+                let next = value[i..].chars().next();
+
+                match next.and_then(|x| escape(dialect, quote, x)) {
+                    Some(x) => {
+                        i += next.map_or(0, char::len_utf8);
+                        push(&mut parts, x.encode_utf8(&mut [0; 4]), false);
+                    }
+                    None => push(&mut parts, "\\", false),
+                }
+            }
+            '$' if expand => match reference(&value[i..], rules) {
+                // References are kept as separate parts, so that `$A$B` is not read as a name
+                Some(len) => {
+                    parts.push((format!("${}", &value[i..i + len]), true));
+                    i += len;
+                }
+                // Compose uses `$$` for a literal `$`
+                None if dialect == Dialect::Compose && value[i..].starts_with('$') => {
+                    push(&mut parts, "$", false);
+                    i += 1;
+                }
+                None => push(&mut parts, "$", false),
+            },
+            _ => push(&mut parts, c.encode_utf8(&mut [0; 4]), false),
+        }
+    }
+
+    parts
+}
@@ -0,0 +1,175 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+/// How the keys which are not valid POSIX names i.e. `[A-Za-z_][A-Za-z0-9_]*` are handled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyPolicy {
+    /// Any key is accepted, even `MY KEY`
+    Permissive,
+
+    /// Invalid keys are skipped
+    Posix,
+
+    /// Invalid keys are an error, which contains the position of the first invalid character
+    Error,
+}
+
+impl Default for KeyPolicy {
+    fn default() -> Self {
+        Self::Permissive
+    }
+}
+
+/// Which definition is used when a key is defined multiple times in the same file. Definitions in
+/// different files, like an included file, always override the earlier ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duplicates {
+    /// Last definition is used, and the earlier ones are overridden
+    LastWins,
+
+    /// First definition is used, and the later ones are ignored
+    FirstWins,
+
+    /// Duplicates are an error, which contains the line numbers of both the definitions
+    Error,
+}
+
+impl Default for Duplicates {
+    fn default() -> Self {
+        Self::LastWins
+    }
+}
+
+/// How the keys are validated and normalized. Normalization happens before the validation, so
+/// `my-key` is a valid key if `uppercase` and `underscore` are enabled
+///
+/// Example
+/// ```
+/// use zenv::{KeyPolicy, Keys, Lines};
+///
+/// let keys = Keys {
+///     policy: KeyPolicy::Error,
+///     uppercase: true,
+///     underscore: true,
+///     ..Keys::default()
+/// };
+///
+/// let parsed = Lines::parse("my-key=1\napp.port=5000", keys).unwrap().to_hash_map();
+///
+/// assert_eq!(parsed["MY_KEY"], "1");
+/// assert_eq!(parsed["APP_PORT"], "5000");
+///
+/// let err = Lines::parse("BASIC=basic\nMY KEY=1", keys).unwrap_err();
+///
+/// assert_eq!(err.to_string(), "Line 2, column 3: Invalid key `MY KEY`, expected [A-Za-z_][A-Za-z0-9_]*");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Keys {
+    /// How the invalid keys are handled
+    pub policy: KeyPolicy,
+
+    /// Convert the keys to uppercase
+    pub uppercase: bool,
+
+    /// Replace `-` and `.` in the keys with `_`
+    pub underscore: bool,
+
+    /// How the duplicate keys are handled, after the normalization
+    pub duplicates: Duplicates,
+}
+
+impl Keys {
+    pub(crate) fn normalize(&self, key: &str) -> String {
+        let key = match self.uppercase {
+            true => key.to_uppercase(),
+            false => key.to_string(),
+        };
+
+        match self.underscore {
+            true => key.replace(|c| c == '-' || c == '.', "_"),
+            false => key,
+        }
+    }
+
+    /// Returns `false` if the duplicate definition at line `n` should be ignored, `first` is the
+    /// line number of the earlier definition in the same file
+    pub(crate) fn duplicate(
+        &self,
+        key: &str,
+        file: Option<&Path>,
+        first: usize,
+        n: usize,
+    ) -> Result<bool> {
+        match self.duplicates {
+            Duplicates::LastWins => Ok(true),
+            Duplicates::FirstWins => Ok(false),
+            Duplicates::Error => {
+                let position = match file {
+                    Some(file) => format!("{}:{}", file.display(), n),
+                    None => format!("Line {}", n),
+                };
+
+                Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "{}: Duplicate key `{}`, first defined at line {}",
+                        position, key, first
+                    ),
+                ))
+            }
+        }
+    }
+
+    // Position of the first character which makes the key invalid, after the normalization
+    fn invalid(&self, key: &str) -> Option<usize> {
+        if key.is_empty() {
+            return Some(0);
+        }
+
+        key.chars().enumerate().position(|(i, c)| {
+            let valid = c.is_ascii_alphabetic()
+                || c == '_'
+                || (i > 0 && c.is_ascii_digit())
+                || (self.underscore && (c == '-' || c == '.'));
+            !valid
+        })
+    }
+
+    /// Returns `false` if the key should be skipped. `n` and `col` are the line and the column of
+    /// the `key`, for the position in the error
+    pub(crate) fn check(
+        &self,
+        key: &str,
+        file: Option<&Path>,
+        n: usize,
+        col: usize,
+    ) -> Result<bool> {
+        if self.policy == KeyPolicy::Permissive {
+            return Ok(true);
+        }
+
+        let at = match self.invalid(key) {
+            Some(x) => x,
+            None => return Ok(true),
+        };
+
+        if self.policy == KeyPolicy::Posix {
+            return Ok(false);
+        }
+
+        let position = match file {
+            Some(file) => format!("{}:{}:{}", file.display(), n, col + at),
+            None => format!("Line {}, column {}", n, col + at),
+        };
+
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{}: Invalid key `{}`, expected [A-Za-z_][A-Za-z0-9_]*",
+                position, key
+            ),
+        ))
+    }
+}
@@ -1,27 +1,113 @@
-use std::{collections::HashMap, env};
+use std::{
+    collections::HashMap,
+    env, fmt,
+    io::Result,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use super::line::{KeyVal, Line, Quote};
+use super::{
+    bash, command,
+    dialect::{Dialect, Part},
+    docker, dotenv,
+    keys::Keys,
+};
+use zenv_core::{marker, KeyVal, Line, Quote, Secrets, Value, MASK};
+
+/// Variables (or commands) substituted into the value of each key
+pub(crate) type Refs = HashMap<String, Vec<String>>;
 
 /// `Lines` is used to parse the sequence of lines
 ///
 /// [`Zenv`](crate::Zenv) is built on top it. And if you want you can directly use this but don't :)
-#[derive(Debug)]
+#[derive(Default)]
 pub struct Lines<'l> {
-    lines: Vec<KeyVal<'l>>,
+    lines: Vec<Entry<'l>>,
+    // Keys which are annotated with `# @secret` comment
+    marked: Vec<String>,
+    keys: Keys,
+    dialect: Dialect,
 }
 
 impl<'l> From<&'l str> for Lines<'l> {
     fn from(lines: &'l str) -> Self {
-        let lines: Vec<KeyVal> = lines
-            .lines()
-            .into_iter()
-            .filter_map(|x| match Line::from(x) {
-                Line::KeyVal(x) => Some(x),
-                _ => None,
-            })
-            .collect();
+        let mut this = Self::default();
+
+        // Includes are only resolved by `Zenv` as it knows where the file lives
+        this.read(lines, None, &mut |_, _| Ok(())).ok();
 
-        Self { lines }
+        this
+    }
+}
+
+/// Parsed `key=val` along with the file, line number (starting from 1) and the text of the line
+/// where it was found. `key` is the normalized key, which is used instead of the one in `kv`.
+/// `parts` are not available in the [`Dialect::Zenv`], where a value is quoted as a whole
+struct Entry<'l> {
+    n: usize,
+    file: Option<&'l Path>,
+    raw: &'l str,
+    key: String,
+    kv: KeyVal<'l>,
+    parts: Option<Vec<Part>>,
+}
+
+/// Where a variable is defined and how its value came to be
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    /// Path of the file, if the lines were read from a file
+    pub file: Option<PathBuf>,
+
+    /// Line number, starting from 1
+    pub line: usize,
+
+    /// Text of the line as it is written in the file
+    pub raw: String,
+
+    /// Quote style of the value
+    pub quote: Quote,
+
+    /// Earlier definitions of the same key which are overridden by this one, in order
+    pub overrides: Vec<Origin>,
+
+    /// Variables, or `$(command)`, which are substituted into the value
+    pub refs: Vec<String>,
+}
+
+impl Origin {
+    /// Whether any variable or command was substituted into the value
+    pub fn is_expanded(&self) -> bool {
+        !self.refs.is_empty()
+    }
+
+    /// Whether the value overrides an earlier definition
+    pub fn is_override(&self) -> bool {
+        !self.overrides.is_empty()
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file.display(), self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+impl<'l> fmt::Debug for Lines<'l> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secrets = Secrets::default();
+
+        f.debug_map()
+            .entries(self.lines.iter().map(|Entry { key, kv, .. }| {
+                let v = match self.marked.contains(key) {
+                    true => MASK,
+                    false => secrets.mask(key, &kv.v),
+                };
+                (key, v)
+            }))
+            .finish()
     }
 }
 
@@ -46,13 +132,316 @@ impl<'l> Lines<'l> {
     pub fn new(lines: Vec<Line<'l>>) -> Self {
         let lines = lines
             .into_iter()
-            .filter_map(|x| match x {
-                Line::KeyVal(x) => Some(x),
+            .enumerate()
+            .filter_map(|(i, x)| match x {
+                Line::KeyVal(kv) => Some(Entry {
+                    n: i + 1,
+                    file: None,
+                    raw: "",
+                    key: kv.k.to_string(),
+                    kv,
+                    parts: None,
+                }),
                 _ => None,
             })
             .collect();
 
-        Self { lines }
+        Self {
+            lines,
+            ..Self::default()
+        }
+    }
+
+    /// Same as [`Lines::from`] but the keys are validated and normalized according to the
+    /// [`Keys`]. See [`Keys`] for an example
+    pub fn parse(lines: &'l str, keys: Keys) -> Result<Self> {
+        Self::parse_with(lines, keys, Dialect::default())
+    }
+
+    /// Same as [`Lines::parse`] but the lines are parsed according to the [`Dialect`]
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{Dialect, Keys, Lines};
+    ///
+    /// const LINES: &str = r#"
+    /// export GREETING="hello"'  world'#not_a_comment
+    /// MULTI='line one
+    /// line two' # comment
+    /// "#;
+    ///
+    /// let parsed = Lines::parse_with(LINES, Keys::default(), Dialect::Bash)
+    ///     .unwrap()
+    ///     .to_hash_map();
+    ///
+    /// assert_eq!(parsed["GREETING"], "hello  world#not_a_comment");
+    /// assert_eq!(parsed["MULTI"], "line one\nline two");
+    /// ```
+    pub fn parse_with(lines: &'l str, keys: Keys, dialect: Dialect) -> Result<Self> {
+        let mut this = Self::with_options(keys, dialect);
+
+        this.read(lines, None, &mut |_, _| Ok(()))?;
+
+        Ok(this)
+    }
+
+    pub(crate) fn with_options(keys: Keys, dialect: Dialect) -> Self {
+        Self {
+            keys,
+            dialect,
+            ..Self::default()
+        }
+    }
+
+    /// Paths included by the text, in order
+    pub(crate) fn includes(text: &str, dialect: Dialect) -> Vec<&str> {
+        let lines: Vec<Line> = match dialect {
+            Dialect::Zenv => text.lines().map(Line::from).collect(),
+            // Errors are reported when the lines are read
+            Dialect::Bash => bash::lines(text, None)
+                .map(|x| x.into_iter().map(|(_, _, _, line, _)| line).collect())
+                .unwrap_or_default(),
+            // Other dotenv implementations don't include files
+            _ => Vec::new(),
+        };
+
+        lines
+            .into_iter()
+            .filter_map(|x| match x {
+                Line::Include(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Parse the lines of a file and append them. `include` is called whenever an include line is
+    /// found, so that the included lines can be appended at the same position
+    pub(crate) fn read<F>(
+        &mut self,
+        lines: &'l str,
+        file: Option<&'l Path>,
+        include: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Self, &'l str) -> Result<()>,
+    {
+        let mut is_secret = false;
+
+        let parsed = match self.dialect {
+            Dialect::Zenv => lines
+                .lines()
+                .enumerate()
+                .map(|(i, x)| (i + 1, Line::column(x), x, Line::from(x), None))
+                .collect(),
+            Dialect::Bash => bash::lines(lines, file)?,
+            Dialect::Docker => docker::lines(lines, file)?,
+            _ => dotenv::lines(lines, file, self.dialect)?,
+        };
+
+        for (n, col, line, parsed, parts) in parsed {
+            match parsed {
+                Line::KeyVal(kv) => {
+                    let marked = std::mem::take(&mut is_secret);
+
+                    if !self.keys.check(kv.k, file, n, col)? {
+                        continue;
+                    }
+
+                    let key = self.keys.normalize(kv.k);
+
+                    let first = self
+                        .lines
+                        .iter()
+                        .find(|x| x.key == key && x.file == file)
+                        .map(|x| x.n);
+
+                    if let Some(first) = first {
+                        if !self.keys.duplicate(&key, file, first, n)? {
+                            continue;
+                        }
+                    }
+
+                    if marked {
+                        self.marked.push(key.clone());
+                    }
+
+                    self.lines.push(Entry {
+                        n,
+                        file,
+                        raw: line,
+                        key,
+                        kv,
+                        parts,
+                    });
+                }
+                Line::Include(path) => {
+                    is_secret = false;
+                    include(self, path)?;
+                }
+                Line::Empty => {
+                    // Annotation only applies to the very next line
+                    is_secret = marker(line);
+                }
+                _ => is_secret = false,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns where the variable is defined. If the key is defined multiple times then the last
+    /// definition is returned, as that is the one which is used, along with the overridden ones.
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{Lines, Quote};
+    ///
+    /// const LINES: &str = r#"
+    /// BASIC=basic
+    /// QUOTED='quoted'
+    /// BASIC="overridden"
+    /// "#;
+    ///
+    /// let origin = Lines::from(LINES).origin("BASIC").unwrap();
+    ///
+    /// assert_eq!(origin.line, 4);
+    /// assert_eq!(origin.file, None);
+    /// assert_eq!(origin.raw, r#"BASIC="overridden""#);
+    /// assert_eq!(origin.quote, Quote::Double);
+    /// assert_eq!(origin.overrides[0].line, 2);
+    /// ```
+    pub fn origin(&self, key: &str) -> Option<Origin> {
+        self.origins().remove(key)
+    }
+
+    /// Sort the variables in the order they are first defined. Variables which are not defined in
+    /// the lines, like the ones read from `KEY_FILE`, are placed at the end sorted by the key.
+    ///
+    /// Example
+    /// ```
+    /// use zenv::Lines;
+    ///
+    /// const LINES: &str = r#"
+    /// B=b
+    /// A=a
+    /// B=bb
+    /// "#;
+    ///
+    /// let lines = Lines::from(LINES);
+    /// let ordered = lines.ordered(lines.to_hash_map());
+    ///
+    /// assert_eq!(ordered, vec![
+    ///     ("B".to_string(), "bb".to_string()),
+    ///     ("A".to_string(), "a".to_string()),
+    /// ]);
+    /// ```
+    pub fn ordered<V>(&self, mut vars: HashMap<String, V>) -> Vec<(String, V)> {
+        let mut ordered = Vec::with_capacity(vars.len());
+
+        for Entry { key, .. } in &self.lines {
+            if let Some(v) = vars.remove(key) {
+                ordered.push((key.to_string(), v));
+            }
+        }
+
+        let mut rest: Vec<(String, V)> = vars.into_iter().collect();
+        rest.sort_by(|a, b| a.0.cmp(&b.0));
+        ordered.extend(rest);
+
+        ordered
+    }
+
+    /// Returns where each variable is defined. See [`Lines::origin`]
+    pub fn origins(&self) -> HashMap<String, Origin> {
+        let mut origins: HashMap<String, Origin> = HashMap::with_capacity(self.lines.len());
+
+        for Entry {
+            n,
+            file,
+            raw,
+            key,
+            kv,
+            ..
+        } in &self.lines
+        {
+            let mut origin = Origin {
+                file: file.map(Path::to_path_buf),
+                line: *n,
+                raw: raw.to_string(),
+                quote: kv.q,
+                overrides: Vec::new(),
+                refs: Vec::new(),
+            };
+
+            if let Some(mut prev) = origins.remove(key) {
+                origin.overrides = std::mem::take(&mut prev.overrides);
+                origin.overrides.push(prev);
+            }
+
+            origins.insert(key.to_string(), origin);
+        }
+
+        origins
+    }
+
+    /// Whether the key is annotated as a secret using a `# @secret` comment on the previous line
+    ///
+    /// Example
+    /// ```
+    /// use zenv::Lines;
+    ///
+    /// const LINES: &str = r#"
+    /// ## @secret
+    /// API_KEY=xyz
+    /// PORT=5000
+    /// "#;
+    ///
+    /// let lines = Lines::from(LINES);
+    ///
+    /// assert!(lines.is_marked("API_KEY"));
+    /// assert!(!lines.is_marked("PORT"));
+    /// ```
+    pub fn is_marked(&self, key: &str) -> bool {
+        self.marked.iter().any(|x| x == key)
+    }
+
+    /// Wrap the parsed variables into [`Value`], which are secret if the key either matches one
+    /// of the `secrets` pattern or is annotated with a `# @secret` comment
+    ///
+    /// Example
+    /// ```
+    /// use zenv::{Lines, Secrets};
+    ///
+    /// const LINES: &str = r#"
+    /// ## @secret
+    /// API_KEY=xyz
+    /// GITHUB_TOKEN=abc
+    /// PORT=5000
+    /// "#;
+    ///
+    /// let lines = Lines::from(LINES);
+    /// let parsed = lines.redact(lines.to_hash_map(), &Secrets::default());
+    ///
+    /// assert!(parsed["API_KEY"].is_secret());
+    /// assert!(parsed["GITHUB_TOKEN"].is_secret());
+    /// assert_eq!(parsed["PORT"].to_string(), "5000");
+    /// assert_eq!(parsed["GITHUB_TOKEN"].to_string(), "***");
+    /// assert_eq!(parsed["GITHUB_TOKEN"].expose(), "abc");
+    /// ```
+    pub fn redact(
+        &self,
+        vars: HashMap<String, String>,
+        secrets: &Secrets,
+    ) -> HashMap<String, Value> {
+        vars.into_iter()
+            .map(|(k, v)| {
+                let v = match self.is_marked(&k) {
+                    true => Value::Secret(crate::Secret::new(v)),
+                    false => secrets.wrap(&k, v),
+                };
+                (k, v)
+            })
+            .collect()
     }
 
     /// Parses the lines and converts into a hashmap
@@ -75,8 +464,8 @@ impl<'l> Lines<'l> {
         let lines = &self.lines;
         let mut hash = HashMap::with_capacity(lines.len());
 
-        for line in lines {
-            hash.insert(line.k.to_string(), line.v.to_string());
+        for Entry { key, kv, .. } in lines {
+            hash.insert(key.to_string(), kv.v.to_string());
         }
 
         hash
@@ -99,69 +488,219 @@ impl<'l> Lines<'l> {
     /// assert_eq!(parsed.get("EXPANDED").unwrap(), &"basic_is_expanded".to_string());
     /// ```
     pub fn expand(&self) -> HashMap<String, String> {
-        let mut vars = Self::to_hash_map(self);
-
-        for line in &self.lines {
-            if let KeyVal {
-                q: Quote::Double,
-                k,
-                v,
-            } = line
-            {
-                let mut new_val = String::with_capacity(v.len());
-                let mut chars = v.chars();
-
-                loop {
-                    match chars.next() {
-                        Some('$') => {
-                            let (key, is_consumed): (String, bool) = match chars.next() {
-                                Some('{') => {
-                                    (chars.by_ref().take_while(|c| c != &'}').collect(), false)
-                                }
-                                Some(x) => {
-                                    let key: String = chars
-                                        .by_ref()
-                                        .take_while(|c| c.is_alphanumeric() || c == &'_')
-                                        .collect();
-
-                                    let k = x.to_string() + &key;
-
-                                    (k, true)
-                                }
-                                _ => (String::with_capacity(0), false),
-                            };
-
-                            let found = match vars.get(&key) {
-                                Some(x) => x.to_string(),
-                                _ => env::var(&key).unwrap_or_default(),
-                            };
-
-                            new_val.push_str(&found);
-
-                            if is_consumed {
-                                // Need to find the terminator charactor
-                                // Which is also consumed by the take_while() above
-                                let idx = chars.clone().count();
-
-                                // If we reach the end of the string
-                                if idx == 0 {
-                                    continue;
-                                }
-
-                                if let Some(consumed) = v.chars().rev().skip(idx).take(1).next() {
-                                    new_val.push(consumed);
-                                };
-                            }
+        // Without command substitution there is nothing that can fail
+        self.substitute(None, &mut HashMap::new())
+            .unwrap_or_default()
+    }
+
+    /// Same as [`Lines::expand`] but also runs the command inside `$(...)` and substitutes it
+    /// with the trimmed stdout of the command. Only double quoted values are considered.
+    ///
+    /// Command is executed using `sh -c` (`cmd /C` on windows) and killed if it doesn't finish
+    /// within the `timeout`. Error includes the line number if the command fails.
+    ///
+    /// _Only enable this for files you trust as it executes arbitrary commands._
+    ///
+    /// Example
+    /// ```
+    /// use std::time::Duration;
+    /// use zenv::Lines;
+    ///
+    /// const LINES: &str = r#"
+    /// BASIC=basic
+    /// COMMAND="$(echo ${BASIC})_from_command"
+    /// "#;
+    ///
+    /// let parsed = Lines::from(LINES)
+    ///     .expand_commands(Duration::from_secs(5))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(parsed.get("COMMAND").unwrap(), &"basic_from_command".to_string());
+    /// ```
+    pub fn expand_commands(&self, timeout: Duration) -> Result<HashMap<String, String>> {
+        self.substitute(Some(timeout), &mut HashMap::new())
+    }
+
+    /// Same as [`Lines::expand`] but also returns the variables (and commands) used in each value
+    pub(crate) fn expand_traced(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<(HashMap<String, String>, Refs)> {
+        let mut refs = HashMap::new();
+        let vars = self.substitute(timeout, &mut refs)?;
+
+        Ok((vars, refs))
+    }
+
+    fn substitute(
+        &self,
+        timeout: Option<Duration>,
+        refs: &mut Refs,
+    ) -> Result<HashMap<String, String>> {
+        // Other tools expand the variables as they go, so a later definition is not visible earlier
+        let sequential = self.dialect != Dialect::Zenv;
+
+        let mut vars = match sequential {
+            true => HashMap::new(),
+            false => Self::to_hash_map(self),
+        };
+
+        for Entry {
+            n, key, kv, parts, ..
+        } in &self.lines
+        {
+            // Only the double quoted values are expanded, unless the value is split into parts
+            let parts = match parts {
+                Some(parts) => parts.iter().map(|(v, e)| (v.as_str(), *e)).collect(),
+                None => vec![(kv.v.as_str(), kv.q == Quote::Double)],
+            };
+
+            if !parts.iter().any(|(_, expand)| *expand) {
+                // Later definition always wins, so the previous references are no longer valid
+                refs.remove(key);
+
+                if sequential {
+                    vars.insert(key.to_string(), kv.v.to_string());
+                }
+
+                continue;
+            }
+
+            let mut used = Vec::new();
+            let mut new_val = String::with_capacity(kv.v.len());
+
+            for (v, expand) in parts {
+                match expand {
+                    true => new_val.push_str(&self.interpolate(v, *n, &vars, timeout, &mut used)?),
+                    false => new_val.push_str(v),
+                }
+            }
+
+            vars.insert(key.to_string(), new_val);
+            refs.insert(key.to_string(), used);
+        }
+
+        Ok(vars)
+    }
+
+    /// Substitute the variables, and the commands if there is a `timeout`, into the value. `used`
+    /// collects the substituted variables and commands
+    fn interpolate(
+        &self,
+        v: &str,
+        n: usize,
+        vars: &HashMap<String, String>,
+        timeout: Option<Duration>,
+        used: &mut Vec<String>,
+    ) -> Result<String> {
+        let mut new_val = String::with_capacity(v.len());
+        let mut chars = v.chars();
+
+        loop {
+            match chars.next() {
+                Some('$') => {
+                    let (key, is_consumed): (String, bool) = match chars.next() {
+                        Some('(') if timeout.is_some() => {
+                            let cmd = command::take(&mut chars);
+                            let timeout = timeout.unwrap_or_default();
+
+                            let out = command::run(&cmd, vars, timeout)
+                                .map_err(|e| command::error(&e, format!("Line {}: {}", n, e)))?;
+
+                            new_val.push_str(&out);
+                            used.push(format!("$({})", cmd));
+                            continue;
+                        }
+                        // Command is kept as it is, if the substitution is disabled
+                        Some('(') => {
+                            new_val.push_str(&format!("$({})", command::take(&mut chars)));
+                            continue;
+                        }
+                        Some('{') if self.dialect != Dialect::Zenv => {
+                            let reference: String =
+                                chars.by_ref().take_while(|c| c != &'}').collect();
+                            let (name, value) = Self::lookup(&reference, vars);
+
+                            new_val.push_str(&value);
+                            used.push(name.to_string());
+                            continue;
+                        }
+                        // References of the dialects are separate parts, so the rest is the name
+                        Some(x) if self.dialect != Dialect::Zenv => {
+                            let name: String = std::iter::once(x).chain(chars.by_ref()).collect();
+
+                            new_val.push_str(&Self::lookup(&name, vars).1);
+                            used.push(name);
+                            continue;
+                        }
+                        Some('{') => (chars.by_ref().take_while(|c| c != &'}').collect(), false),
+                        Some(x) => {
+                            let key: String = chars
+                                .by_ref()
+                                .take_while(|c| c.is_alphanumeric() || c == &'_')
+                                .collect();
+
+                            let k = x.to_string() + &key;
+
+                            (k, true)
                         }
-                        Some(a) => new_val.push(a),
-                        _ => break,
+                        _ => (String::with_capacity(0), false),
+                    };
+
+                    let found = match vars.get(&key) {
+                        Some(x) => x.to_string(),
+                        _ => env::var(&key).unwrap_or_default(),
+                    };
+
+                    new_val.push_str(&found);
+                    used.push(key);
+
+                    if is_consumed {
+                        // Need to find the terminator charactor
+                        // Which is also consumed by the take_while() above
+                        let idx = chars.clone().count();
+
+                        // If we reach the end of the string
+                        if idx == 0 {
+                            continue;
+                        }
+
+                        if let Some(consumed) = v.chars().rev().skip(idx).take(1).next() {
+                            new_val.push(consumed);
+                        };
                     }
                 }
-
-                vars.insert(k.to_string(), new_val);
+                Some(a) => new_val.push(a),
+                _ => break,
             }
         }
 
-        vars
+        Ok(new_val)
+    }
+
+    /// Name and the value of the `${NAME}` reference, which can also have a default value i.e.
+    /// `${NAME:-default}` if it is unset or empty, and `${NAME-default}` if it is unset
+    fn lookup<'r>(reference: &'r str, vars: &HashMap<String, String>) -> (&'r str, String) {
+        let (name, default, empty) = match reference.find('-') {
+            Some(i) => match reference[..i].strip_suffix(':') {
+                Some(name) => (name, Some(&reference[i + 1..]), true),
+                None => (&reference[..i], Some(&reference[i + 1..]), false),
+            },
+            None => (reference, None, false),
+        };
+
+        let found = vars
+            .get(name)
+            .cloned()
+            .or_else(|| env::var(name).ok())
+            .filter(|x| !(empty && x.is_empty()));
+
+        let value = match (found, default) {
+            (Some(x), _) => x,
+            (None, Some(x)) => x.to_string(),
+            (None, None) => String::new(),
+        };
+
+        (name, value)
     }
 }
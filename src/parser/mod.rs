@@ -1,5 +1,11 @@
-mod line;
+mod bash;
+mod command;
+mod dialect;
+mod docker;
+mod dotenv;
+mod keys;
 mod lines;
 
-pub use line::*;
+pub use dialect::*;
+pub use keys::*;
 pub use lines::*;
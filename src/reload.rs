@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::Result,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{Value, Zenv};
+
+/// Maximum time the background thread sleeps at once, so that it can be stopped quickly
+const TICK: Duration = Duration::from_millis(50);
+
+/// Change in a variable compared to the last time the file was parsed. Values are masked in the
+/// [`Debug`](std::fmt::Debug) output if the key is a secret.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// When the variable is newly defined
+    Added {
+        /// Key of the variable
+        key: String,
+        /// Value of the variable
+        val: Value,
+    },
+
+    /// When the value of the variable is changed
+    Changed {
+        /// Key of the variable
+        key: String,
+        /// Previous value of the variable
+        old: Value,
+        /// Current value of the variable
+        new: Value,
+    },
+
+    /// When the variable is no longer defined
+    Removed {
+        /// Key of the variable
+        key: String,
+        /// Previous value of the variable
+        val: Value,
+    },
+}
+
+impl Change {
+    /// Key of the changed variable
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Added { key, .. } | Self::Changed { key, .. } | Self::Removed { key, .. } => key,
+        }
+    }
+
+    /// Sets, or removes, the variable in the process environment
+    pub fn apply(&self) {
+        match self {
+            Self::Added { key, val } | Self::Changed { key, new: val, .. } => {
+                env::set_var(key, val.expose())
+            }
+            Self::Removed { key, .. } => env::remove_var(key),
+        }
+    }
+
+    /// Compares two sets of ordered variables. Added and changed variables are in the order of
+    /// `new`, followed by the removed ones in the order of `old`.
+    pub(crate) fn diff(old: &[(String, Value)], new: &[(String, Value)]) -> Vec<Self> {
+        let prev: HashMap<&str, &Value> = old.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        let next: HashMap<&str, &Value> = new.iter().map(|(k, v)| (k.as_str(), v)).collect();
+
+        let mut changes = Vec::new();
+
+        for (key, val) in new {
+            match prev.get(key.as_str()) {
+                None => changes.push(Self::Added {
+                    key: key.to_string(),
+                    val: val.clone(),
+                }),
+                Some(old) if old.expose() != val.expose() => changes.push(Self::Changed {
+                    key: key.to_string(),
+                    old: (*old).clone(),
+                    new: val.clone(),
+                }),
+                _ => {}
+            }
+        }
+
+        for (key, val) in old {
+            if !next.contains_key(key.as_str()) {
+                changes.push(Self::Removed {
+                    key: key.to_string(),
+                    val: val.clone(),
+                });
+            }
+        }
+
+        changes
+    }
+}
+
+/// Handle to the background thread started by [`Zenv::watch`]. Watching stops when this is dropped
+#[derive(Debug)]
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Stops watching the files, same as dropping the watcher
+    pub fn stop(self) {}
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+/// Modified time and the size of each file, `None` if the file is missing
+pub(crate) fn stamp(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|p| {
+            let meta = fs::metadata(p).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        })
+        .collect()
+}
+
+pub(crate) fn watch<F>(zenv: Zenv, interval: Duration, mut callback: F) -> Result<Watcher>
+where
+    F: FnMut(Result<Vec<Change>>) + Send + 'static,
+{
+    let mut last = zenv.parse_ordered_redacted()?;
+    let mut paths = zenv.paths()?;
+    let mut stamps = stamp(&paths);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = Arc::clone(&stop);
+
+    let handle = thread::spawn(move || loop {
+        let start = Instant::now();
+
+        while start.elapsed() < interval {
+            if stopped.load(Ordering::SeqCst) {
+                return;
+            }
+
+            thread::sleep(TICK.min(interval));
+        }
+
+        let now = stamp(&paths);
+
+        if now == stamps {
+            continue;
+        }
+
+        stamps = now;
+
+        let parsed = zenv
+            .parse_ordered_redacted()
+            .and_then(|vars| Ok((vars, zenv.paths()?)));
+
+        match parsed {
+            Ok((vars, new_paths)) => {
+                // Included files might have changed as well
+                paths = new_paths;
+                stamps = stamp(&paths);
+
+                let changes = Change::diff(&last, &vars);
+                last = vars;
+
+                if !changes.is_empty() {
+                    callback(Ok(changes));
+                }
+            }
+            // Last good variables are kept, so that the next diff is against them
+            Err(e) => callback(Err(e)),
+        }
+    });
+
+    Ok(Watcher {
+        stop,
+        handle: Some(handle),
+    })
+}
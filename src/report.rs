@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    path::PathBuf,
+};
+use zenv::{Origin, Secrets, Value, MASK};
+
+type Parsed = HashMap<String, (Value, Origin)>;
+
+// Variables grouped by the file, in the order the files are read, and then by the line
+fn sorted<'a>(paths: &[PathBuf], parsed: &'a Parsed) -> Vec<(&'a String, &'a Value, &'a Origin)> {
+    let mut sorted: Vec<_> = parsed.iter().map(|(k, (v, o))| (k, v, o)).collect();
+
+    sorted.sort_by_key(|(k, _, o)| {
+        let file = o
+            .file
+            .as_ref()
+            .and_then(|f| paths.iter().position(|p| p == f))
+            .unwrap_or(usize::MAX);
+
+        (file, o.line, k.to_string())
+    });
+
+    sorted
+}
+
+// Old value is as secret as the new one, even if the key doesn't match any of the patterns
+fn was<'a>(key: &str, val: &Value, old: &'a str, secrets: &Secrets) -> &'a str {
+    match val.is_secret() {
+        true => MASK,
+        false => secrets.mask(key, old),
+    }
+}
+
+fn quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+
+    if arg.is_empty() || arg.contains(char::is_whitespace) {
+        format!("{:?}", arg)
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Logs which files are read, and which variables are set, overridden or skipped
+pub fn verbose(paths: &[PathBuf], parsed: &Parsed, secrets: &Secrets, keep: Option<&[String]>) {
+    for path in paths {
+        eprintln!("[zenv] read {}", path.display());
+    }
+
+    for (key, val, origin) in sorted(paths, parsed) {
+        for o in &origin.overrides {
+            eprintln!("[zenv] {} from {} is overridden by {}", key, o, origin);
+        }
+
+        if origin.is_expanded() {
+            eprintln!(
+                "[zenv] {} is expanded from {} to {}",
+                key,
+                origin.refs.join(", "),
+                val
+            );
+        }
+
+        match env::var(key) {
+            Ok(x) if x != val.expose() => eprintln!(
+                "[zenv] override {}={} (was {}) from {}",
+                key,
+                val,
+                was(key, val, &x, secrets),
+                origin
+            ),
+            _ => eprintln!("[zenv] set {}={} from {}", key, val, origin),
+        }
+    }
+
+    for key in keep.unwrap_or_default() {
+        if parsed.contains_key(key) {
+            eprintln!(
+                "[zenv] skip {} as it is already defined in the env file",
+                key
+            );
+        } else if env::var_os(key).is_none() {
+            eprintln!("[zenv] skip {} as it is not set in the environment", key);
+        } else {
+            eprintln!("[zenv] keep {} from the environment", key);
+        }
+    }
+}
+
+/// Prints the command, the working directory and the changes in the environment without running
+/// the command
+pub fn dry_run(
+    cmd: &OsStr,
+    args: &[OsString],
+    paths: &[PathBuf],
+    parsed: &Parsed,
+    secrets: &Secrets,
+    keep: Option<&[String]>,
+) {
+    let mut command = vec![quote(cmd)];
+    command.extend(args.iter().map(|x| quote(x)));
+
+    println!("command: {}", command.join(" "));
+
+    if let Ok(cwd) = env::current_dir() {
+        println!("cwd: {}", cwd.display());
+    }
+
+    println!("env:");
+
+    for (key, val, _) in sorted(paths, parsed) {
+        match env::var(key) {
+            Ok(x) if x == val.expose() => {}
+            Ok(x) => println!("  ~ {}={} (was {})", key, val, was(key, val, &x, secrets)),
+            Err(_) => println!("  + {}={}", key, val),
+        }
+    }
+
+    // Everything which is not kept is removed when the environment is cleared
+    if let Some(keep) = keep {
+        let mut removed: Vec<String> = env::vars_os()
+            .map(|(k, _)| k.to_string_lossy().to_string())
+            .filter(|k| !parsed.contains_key(k) && !keep.contains(k))
+            .collect();
+
+        removed.sort();
+
+        for key in removed {
+            println!("  - {}", key);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    cell::Cell,
+    env,
+    ffi::OsString,
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Mutex, MutexGuard, Once, PoisonError,
+    },
+};
+
+static INIT: Once = Once::new();
+static LOCK: AtomicPtr<Mutex<()>> = AtomicPtr::new(ptr::null_mut());
+
+thread_local! {
+    // Guards alive on the current thread, nested guards reuse the lock of the outermost one
+    static DEPTH: Cell<usize> = Cell::new(0);
+}
+
+// Global lock which is held by every guard, `Mutex::new` is not const in our MSRV
+fn lock() -> MutexGuard<'static, ()> {
+    INIT.call_once(|| {
+        LOCK.store(Box::into_raw(Box::new(Mutex::new(()))), Ordering::SeqCst);
+    });
+
+    // SAFETY: pointer is set exactly once by `INIT`, and is never freed
+    let lock = unsafe { &*LOCK.load(Ordering::SeqCst) };
+
+    // Lock is only used for ordering, so a guard dropped while panicking doesn't matter
+    lock.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Guard returned by [`Zenv::scoped`](crate::Zenv::scoped) which restores the environment, as it
+/// was before the variables were set, when it is dropped
+#[derive(Debug)]
+pub struct Scoped {
+    previous: Vec<(String, Option<OsString>)>,
+    // Only the outermost guard of the thread holds the lock
+    _lock: Option<MutexGuard<'static, ()>>,
+}
+
+impl Scoped {
+    /// Sets the variables while holding the global lock, so that only one thread has guards at a
+    /// time. Locking again on the same thread would deadlock, so the nested guards don't
+    pub(crate) fn new(vars: Vec<(String, String)>) -> Self {
+        let lock = match DEPTH.with(Cell::get) {
+            0 => Some(lock()),
+            _ => None,
+        };
+
+        DEPTH.with(|x| x.set(x.get() + 1));
+
+        let previous = vars
+            .into_iter()
+            .map(|(key, val)| {
+                let prev = env::var_os(&key);
+                env::set_var(&key, val);
+                (key, prev)
+            })
+            .collect();
+
+        Self {
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+impl Drop for Scoped {
+    fn drop(&mut self) {
+        for (key, prev) in self.previous.drain(..).rev() {
+            match prev {
+                Some(val) => env::set_var(key, val),
+                None => env::remove_var(key),
+            }
+        }
+
+        DEPTH.with(|x| x.set(x.get() - 1));
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    io, mem, ptr,
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+use libc::siginfo_t;
+use std::os::raw::{c_int, c_void};
+
+/// Signals which are forwarded to the child
+const SIGNALS: [c_int; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+/// PID of the running child, zero if there is none
+static CHILD: AtomicI32 = AtomicI32::new(0);
+
+/// Last signal received by zenv, zero if there is none
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn handle(sig: c_int, info: *mut siginfo_t, _: *mut c_void) {
+    RECEIVED.store(sig, Ordering::SeqCst);
+
+    let pid = CHILD.load(Ordering::SeqCst);
+
+    // Signals generated by the terminal (Ctrl-C etc.) have no sender and are already delivered
+    // to the whole foreground process group, which includes the child. So only the signals sent
+    // by other processes, like supervisors or `kill`, are forwarded.
+    let sender = unsafe { (*info).si_pid() };
+
+    if pid > 0 && sender != 0 {
+        unsafe { libc::kill(pid, sig) };
+    }
+}
+
+/// Forward SIGINT, SIGTERM, SIGHUP and SIGQUIT received by zenv to the child. This also keeps
+/// zenv alive until the child exits, so that the exit status can be reported.
+pub fn forward(pid: u32) -> io::Result<()> {
+    CHILD.store(pid as i32, Ordering::SeqCst);
+
+    for sig in SIGNALS {
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handle as *const () as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+
+            if libc::sigaction(sig, &action, ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the last signal received by zenv, if any
+pub fn received() -> Option<i32> {
+    Some(RECEIVED.load(Ordering::SeqCst)).filter(|x| *x != 0)
+}
+
+/// Ask the process to terminate gracefully
+pub fn terminate(pid: u32) -> io::Result<()> {
+    match unsafe { libc::kill(pid as i32, libc::SIGTERM) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
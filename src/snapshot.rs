@@ -0,0 +1,92 @@
+use std::{
+    collections::HashMap,
+    env::{self, VarError},
+    io::{Error, ErrorKind, Result},
+    ptr,
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Once,
+    },
+};
+
+use crate::Zenv;
+
+static INIT: Once = Once::new();
+static SNAPSHOT: AtomicPtr<HashMap<String, String>> = AtomicPtr::new(ptr::null_mut());
+
+/// Store the variables as the snapshot, unless it is already initialized
+pub(crate) fn init(vars: HashMap<String, String>) -> Result<()> {
+    let mut stored = false;
+
+    INIT.call_once(|| {
+        SNAPSHOT.store(Box::into_raw(Box::new(vars)), Ordering::SeqCst);
+        stored = true;
+    });
+
+    if stored {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "Snapshot is already initialized",
+        ))
+    }
+}
+
+fn snapshot() -> &'static HashMap<String, String> {
+    // Lazily read the `.env` file, same as `zenv!()`
+    INIT.call_once(|| {
+        let vars = Zenv::new(".env", false).parse().unwrap_or_default();
+        SNAPSHOT.store(Box::into_raw(Box::new(vars)), Ordering::SeqCst);
+    });
+
+    // SAFETY: pointer is set exactly once by `INIT`, and is never freed
+    unsafe { &*SNAPSHOT.load(Ordering::SeqCst) }
+}
+
+/// Returns the variable from the snapshot, or from the process environment if the snapshot
+/// doesn't have it. Unlike [`Zenv::configure`], the snapshot never modifies the process
+/// environment so it is safe to use from multiple threads.
+///
+/// The snapshot is initialized by [`Zenv::init`], or `zenv!(init)`, otherwise it is read from the
+/// `.env` file on the first access.
+///
+/// Example
+/// ```
+/// zenv::Zenv::new("tests/.env.basic", false).init().unwrap();
+///
+/// assert_eq!(zenv::var("BASIC"), Ok("basic".to_string()));
+/// assert!(std::env::var("BASIC").is_err());
+/// ```
+pub fn var(key: &str) -> std::result::Result<String, VarError> {
+    match snapshot().get(key) {
+        Some(val) => Ok(val.to_string()),
+        None => env::var(key),
+    }
+}
+
+/// Returns the variables from the process environment along with the ones from the snapshot,
+/// which take precedence. See [`var`]
+///
+/// Example
+/// ```
+/// zenv::Zenv::new("tests/.env.basic", false).init().unwrap();
+///
+/// let vars = zenv::vars();
+///
+/// assert_eq!(vars["BASIC"], "basic");
+/// assert!(vars.contains_key("PATH"));
+/// ```
+pub fn vars() -> HashMap<String, String> {
+    let mut vars: HashMap<String, String> = env::vars_os()
+        .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+        .collect();
+
+    vars.extend(
+        snapshot()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+    );
+
+    vars
+}
@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    process::{Child, Command},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+use zenv::Zenv;
+
+/// How often the env files and the command are checked
+const POLL: Duration = Duration::from_millis(250);
+
+fn spawn(mut command: Command) -> Result<Child, String> {
+    let child = command.spawn().map_err(|_| "Unable to spawn program!")?;
+
+    #[cfg(unix)]
+    crate::signal::forward(child.id()).map_err(|e| e.to_string())?;
+
+    Ok(child)
+}
+
+// Asks the command to terminate and kills it if it is still running after the grace period
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn stop(child: &mut Child, grace: Duration) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        crate::signal::terminate(child.id()).ok();
+
+        let start = Instant::now();
+
+        while start.elapsed() < grace {
+            if child.try_wait().map_err(|e| e.to_string())?.is_some() {
+                return Ok(());
+            }
+
+            thread::sleep(POLL);
+        }
+    }
+
+    child.kill().ok();
+    child.wait().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Runs the command and restarts it with the new variables whenever any variable in the env file,
+/// or in any of the file it includes, is changed. If the file can't be parsed then the error is
+/// reported and the command keeps running with the old variables.
+pub fn watch<F>(zenv: &Zenv, grace: Duration, mut command: F) -> Result<i32, String>
+where
+    F: FnMut(HashMap<OsString, OsString>) -> Command,
+{
+    let (tx, rx) = mpsc::channel();
+
+    // Changes are only compared to restart the command, which gets the variables as they are
+    let _watcher = zenv
+        .clone()
+        .lossy(true)
+        .watch(POLL, move |changes| {
+            tx.send(changes).ok();
+        })
+        .map_err(|e| e.to_string())?;
+
+    // Env files can have arbitrary bytes, same as the environment
+    let mut vars = zenv.parse_os().map_err(|e| e.to_string())?;
+    let mut child = Some(spawn(command(vars.clone()))?);
+
+    loop {
+        #[cfg(unix)]
+        if let Some(sig) = crate::signal::received() {
+            if let Some(c) = child.as_mut() {
+                stop(c, grace)?;
+            }
+
+            return Ok(128 + sig);
+        }
+
+        if let Some(c) = child.as_mut() {
+            if let Some(status) = c.try_wait().map_err(|e| e.to_string())? {
+                eprintln!("[zenv] command exited with {}, waiting for changes", status);
+                child = None;
+            }
+        }
+
+        vars = match rx.recv_timeout(POLL) {
+            Ok(Ok(_)) => match zenv.parse_os() {
+                Ok(vars) => vars,
+                Err(e) => {
+                    eprintln!("[zenv] {}, command is not restarted", e);
+                    continue;
+                }
+            },
+            Ok(Err(e)) => {
+                eprintln!("[zenv] {}, command is not restarted", e);
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Err("Stopped watching the files".into()),
+        };
+
+        if let Some(mut c) = child.take() {
+            stop(&mut c, grace)?;
+        }
+
+        eprintln!("[zenv] env file changed, restarting the command");
+        child = Some(spawn(command(vars.clone()))?);
+    }
+}
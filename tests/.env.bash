@@ -0,0 +1,22 @@
+# Sourced by bash in the tests, so it needs to mean the same thing
+set -a
+export BASIC=basic
+URL=localhost#main # comment
+ADJACENT="foo"'bar'baz
+SINGLE='no\nescape $BASIC'
+ANSI=$'tab\tnewline\n'
+ESCAPED=a\ b\$BASIC
+EXPANDED="${BASIC}_$BASIC"'_$BASIC'
+MULTI="line one
+line two"
+CONTINUED=one\
+two
+PORT=5000; HOST=localhost
+ORDERED="$PORT"
+PORT=8080
+JOINED=$BASIC$BASIC
+PATH_LIKE=$BASIC:/x
+SPACED="$BASIC $BASIC"
+SLASH="$BASIC/x"
+POSITIONAL=$12
+set +a
@@ -0,0 +1,440 @@
+#![cfg(all(feature = "cli", unix))]
+
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+const ZENV: &str = env!("CARGO_BIN_EXE_zenv");
+
+// Unique temporary directory for each test
+fn tmp(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("zenv-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn wait_for<F: Fn() -> bool>(f: F) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs(10) {
+        if f() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    false
+}
+
+#[test]
+fn exit_code() {
+    let status = Command::new(ZENV)
+        .args(["-f", "tests/.env.basic", "--", "sh", "-c", "exit 3"])
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn exit_code_signal() {
+    let status = Command::new(ZENV)
+        .args(["-f", "tests/.env.basic", "--", "sh", "-c", "kill -TERM $$"])
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(128 + 15));
+}
+
+#[test]
+fn exec() {
+    let zenv = Command::new(ZENV)
+        .args([
+            "--exec",
+            "-f",
+            "tests/.env.basic",
+            "--",
+            "sh",
+            "-c",
+            "echo $$; exit 4",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let pid = zenv.id();
+    let out = zenv.wait_with_output().unwrap();
+
+    // Command replaces zenv, so it has the same PID and exit code
+    assert_eq!(out.status.code(), Some(4));
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), format!("{}\n", pid));
+
+    let out = Command::new(ZENV)
+        .args(["--exec", "--watch", "-f", "tests/.env.basic", "--", "true"])
+        .output()
+        .unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(out.stderr).unwrap(),
+        "--watch can't be used with --exec\n"
+    );
+}
+
+#[test]
+fn watch_restarts() {
+    let dir = tmp("watch");
+    let env_file = dir.join(".env");
+    let out = dir.join("out");
+
+    fs::write(&env_file, "VALUE=first\n").unwrap();
+
+    let mut zenv = Command::new(ZENV)
+        .args(["--watch", "--includes"])
+        .arg("-f")
+        .arg(&env_file)
+        .args(["--", "sh", "-c"])
+        .arg(format!("echo $VALUE >> {}; sleep 30", out.display()))
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let read = || fs::read(&out).unwrap_or_default();
+
+    assert!(wait_for(|| read() == b"first\n"));
+
+    // Parse errors shouldn't restart the command
+    fs::write(&env_file, "#include missing\n").unwrap();
+    thread::sleep(Duration::from_secs(1));
+    assert_eq!(read(), b"first\n");
+
+    // Invalid UTF-8 is kept as it is, same as without watching
+    fs::write(&env_file, b"VALUE=second\xffvalue\n").unwrap();
+
+    assert!(wait_for(|| read() == b"first\nsecond\xffvalue\n"));
+
+    // Signal is forwarded to the command, and zenv exits once it is stopped
+    Command::new("kill")
+        .arg(zenv.id().to_string())
+        .status()
+        .unwrap();
+
+    assert_eq!(zenv.wait().unwrap().code(), Some(128 + 15));
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn dry_run() {
+    let out = Command::new(ZENV)
+        .args(["--dry-run", "-f", "tests/.env.secret", "-s", "PORT"])
+        .args(["--", "sh", "-c", "exit 3"])
+        .env_remove("PORT")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+
+    // Command is not executed
+    assert_eq!(out.status.code(), Some(0));
+    assert!(stdout.contains(r#"command: sh -c "exit 3""#));
+    assert!(stdout.contains("+ PORT=***"));
+    assert!(stdout.contains("+ GITHUB_TOKEN=***"));
+    assert!(!stdout.contains("ghp_token"));
+}
+
+#[test]
+fn commands_run_once() {
+    let dir = tmp("commands");
+    let env_file = dir.join(".env");
+    let count = dir.join("count");
+
+    fs::write(
+        &env_file,
+        format!("RUN=\"$(echo run >> {}; echo done)\"\n", count.display()),
+    )
+    .unwrap();
+
+    let out = Command::new(ZENV)
+        .args(["-x", "-c", "--verbose", "-f"])
+        .arg(&env_file)
+        .args(["--", "sh", "-c", r#"printf '%s' "$RUN""#])
+        .output()
+        .unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"done");
+    assert_eq!(fs::read_to_string(&count).unwrap(), "run\n");
+
+    // Dry run shows the command as it is written, without running it
+    let out = Command::new(ZENV)
+        .args(["-x", "-c", "--dry-run", "-f"])
+        .arg(&env_file)
+        .args(["--", "true"])
+        .env_remove("RUN")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+
+    assert!(stdout.contains("+ RUN=$(echo run >> "));
+    assert_eq!(fs::read_to_string(&count).unwrap(), "run\n");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn marked_secret_was() {
+    // API_KEY is only marked with `# @secret`, its old value is masked as well
+    let out = Command::new(ZENV)
+        .args(["--dry-run", "--verbose", "-f", "tests/.env.secret"])
+        .args(["--", "true"])
+        .env("API_KEY", "old_api_key")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+
+    assert!(stdout.contains("~ API_KEY=*** (was ***)"));
+    assert!(stderr.contains("override API_KEY=*** (was ***)"));
+    assert!(!stdout.contains("old_api_key"));
+    assert!(!stderr.contains("old_api_key"));
+}
+
+#[test]
+fn stdin_and_overrides() {
+    let mut zenv = Command::new(ZENV)
+        .args(["-f", "-", "--env-string", r"B=string\nC=string"])
+        .args(["-e", "C=flag", "-e", "D=a b # c"])
+        .args(["--", "sh", "-c", r#"printf '%s|' "$A" "$B" "$C" "$D""#])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = zenv.stdin.take().unwrap();
+    stdin.write_all(b"A=stdin\nB=stdin\n").unwrap();
+    drop(stdin);
+
+    let out = zenv.wait_with_output().unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "stdin|string|flag|a b # c|"
+    );
+
+    // Stdin has no file to watch
+    let out = Command::new(ZENV)
+        .args(["--watch", "-f", "-", "--", "true"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(out.stderr).unwrap(),
+        "--watch can't be used with -f -\n"
+    );
+}
+
+#[test]
+fn clear() {
+    let out = Command::new(ZENV)
+        .args([
+            "--clear",
+            "-k",
+            "KEPT",
+            "-f",
+            "tests/.env.basic",
+            "--",
+            "env",
+        ])
+        .env("KEPT", "kept")
+        .env("DROPPED", "dropped")
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let mut vars: Vec<&str> = stdout.lines().collect();
+    vars.sort_unstable();
+
+    // Only the env file and the kept variables are passed to the command
+    assert!(out.status.success());
+    assert_eq!(
+        vars,
+        [
+            "BASIC=basic",
+            "DOUBLE_QUOTES=double_quotes",
+            "EMPTY=",
+            "EXPORTED=exported",
+            "KEPT=kept",
+            "SINGLE_QUOTES=single_quotes",
+        ]
+    );
+}
+
+#[test]
+fn non_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = tmp("non-utf8");
+    let path = dir.join(OsStr::from_bytes(b".env.\xff"));
+    fs::write(&path, b"INVALID=in\xffvalid\n").unwrap();
+
+    let out = Command::new(ZENV)
+        .arg("-f")
+        .arg(&path)
+        .args(["--", "sh", "-c", r#"printf '%s' "$INVALID""#])
+        .output()
+        .unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"in\xffvalid");
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn filter() {
+    let mut zenv = Command::new(ZENV)
+        .args(["-f", "-", "--prefix", "API_", "--strip-prefix"])
+        .args(["--exclude", "*_TOKEN", "--only", "API_PORT,API_TOKEN"])
+        .args([
+            "--",
+            "sh",
+            "-c",
+            r#"printf '%s|' "$PORT" "$TOKEN" "$API_PORT" "$HOST""#,
+        ])
+        .env_remove("HOST")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = zenv.stdin.take().unwrap();
+    stdin
+        .write_all(b"HOST=localhost\nAPI_PORT=5000\nAPI_TOKEN=token\nAPI_URL=url\n")
+        .unwrap();
+    drop(stdin);
+
+    let out = zenv.wait_with_output().unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "5000||||");
+}
+
+#[test]
+fn compat_check() {
+    let out = Command::new(ZENV)
+        .args(["compat-check", "-x", "-f", "tests/.env.dialects"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout.contains("URL\n    zenv      \"localhost\"\n    bash      \"localhost#main\""));
+    assert!(!stdout.contains("BASIC\n"));
+
+    let out = Command::new(ZENV)
+        .args(["compat-check", "-f", "tests/.env.common"])
+        .output()
+        .unwrap();
+
+    assert_eq!(out.status.code(), Some(0));
+}
+
+#[test]
+fn subcommand_name() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Program which has the same name as a subcommand
+    let dir = tmp("subcommand");
+    let program = dir.join("convert");
+    fs::write(&program, "#!/bin/sh\nprintf '%s %s' \"$BASIC\" \"$*\"\n").unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let path = format!("{}:{}", dir.display(), env::var("PATH").unwrap_or_default());
+
+    for args in [
+        &["convert", "-f", "tests/.env.basic", "a.png", "b.jpg"][..],
+        &["convert", "-f", "tests/.env.basic", "--", "a.png", "b.jpg"][..],
+    ] {
+        let out = Command::new(ZENV)
+            .args(args)
+            .env("PATH", &path)
+            .output()
+            .unwrap();
+
+        assert!(out.status.success());
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), "basic a.png b.jpg");
+    }
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn convert() {
+    let out = Command::new(ZENV)
+        .args([
+            "convert",
+            "--to",
+            "docker-env",
+            "-x",
+            "-f",
+            "tests/.env.duplicates",
+        ])
+        .args(["-e", "HOST=example.com"])
+        .output()
+        .unwrap();
+
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "PORT=8080\nHOST=example.com\nURL=localhost:8080\n"
+    );
+
+    let out = Command::new(ZENV)
+        .args(["convert", "--to", "docker-env", "-d", "compose"])
+        .args(["-f", "tests/.env.dialects"])
+        .output()
+        .unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(out.stderr).unwrap(),
+        "Value of `NEWLINE` has a line break, which a docker env file can't represent\n"
+    );
+
+    // Compose reads the same values back, even though they have quotes and `$`
+    let out = Command::new(ZENV)
+        .args(["convert", "--to", "compose-env", "-d", "docker"])
+        .args(["-f", "tests/.env.docker"])
+        .output()
+        .unwrap();
+
+    assert!(out.status.success());
+
+    let path = tmp("convert").join(".env.compose");
+    fs::write(&path, out.stdout).unwrap();
+
+    let read = |dialect: &str, path: &str| {
+        let out = Command::new(ZENV)
+            .args(["-d", dialect, "-x", "-f", path, "--clear", "--", "env"])
+            .output()
+            .unwrap();
+        String::from_utf8(out.stdout).unwrap()
+    };
+
+    let docker = read("docker", "tests/.env.docker");
+
+    assert!(docker.contains("QUOTED=\"quoted\"\n"));
+    assert_eq!(read("compose", path.to_str().unwrap()), docker);
+}
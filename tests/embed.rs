@@ -0,0 +1,50 @@
+#![cfg(feature = "macros")]
+
+use zenv::{Embedded, Zenv};
+
+static BASIC: Embedded = zenv::include!("tests/.env.basic");
+
+#[test]
+fn embed_basic() {
+    let z = Zenv::new("tests/.env.basic", false)
+        .parse_ordered()
+        .unwrap();
+    let embedded: Vec<(String, String)> = BASIC
+        .vars()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    // Same as parsing the file at runtime
+    assert_eq!(embedded, z);
+    assert_eq!(BASIC.get("BASIC"), Some("basic"));
+    assert_eq!(BASIC.get("MISSING"), None);
+}
+
+#[test]
+fn embed_include() {
+    let env = zenv::include!("tests/include/.env", includes);
+
+    assert_eq!(
+        env.to_hash_map(),
+        Zenv::new("tests/include/.env", false)
+            .includes(true)
+            .parse()
+            .unwrap()
+    );
+    assert_eq!(env.get("OVERRIDDEN"), Some("overridden"));
+
+    env.configure();
+    assert_eq!(std::env::var("LOCAL").unwrap(), "local");
+}
+
+#[test]
+fn embed_debug() {
+    let env = zenv::include!("tests/.env.secret");
+
+    let debug = format!("{:?}", env);
+
+    assert!(!debug.contains("ghp_token"));
+    assert!(!debug.contains("api_key"));
+    assert!(debug.contains("5000"));
+}
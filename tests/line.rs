@@ -321,3 +321,72 @@ fn comment_at_end_dquote() {
         )
     )
 }
+
+#[test]
+fn secret_masked_in_debug() {
+    let res = Line::from("API_TOKEN=token");
+
+    assert_eq!(
+        format!("{:?}", res),
+        r#"KeyVal(KeyVal { k: "API_TOKEN", v: "***", q: No })"#
+    )
+}
+
+#[test]
+fn include_directive() {
+    assert_eq!(
+        Line::from("#include .env.common"),
+        Line::Include(".env.common")
+    );
+    assert_eq!(Line::from("source '../.env'"), Line::Include("../.env"));
+    assert_eq!(
+        Line::from(". \"shared/.env\""),
+        Line::Include("shared/.env")
+    );
+    assert_eq!(Line::from("#include"), Line::Empty);
+    assert_eq!(Line::from("# include is just a comment"), Line::Empty);
+}
+
+#[test]
+fn bom_and_crlf() {
+    let unquoted = Line::KeyVal(KeyVal {
+        k: "BASIC",
+        v: "basic".to_string(),
+        q: Quote::No,
+    });
+
+    assert_eq!(Line::from("\u{feff}BASIC=basic\r"), unquoted);
+    assert_eq!(Line::from("BASIC=basic # comment\r"), unquoted);
+
+    assert_eq!(
+        Line::from("\u{feff}S_QUOTE='single'\r"),
+        Line::KeyVal(KeyVal {
+            k: "S_QUOTE",
+            v: "single".to_string(),
+            q: Quote::Single,
+        })
+    );
+    assert_eq!(
+        Line::from("D_QUOTE=\"double\"\r"),
+        Line::KeyVal(KeyVal {
+            k: "D_QUOTE",
+            v: "double".to_string(),
+            q: Quote::Double,
+        })
+    );
+    assert_eq!(
+        Line::from("EMPTY=\r"),
+        Line::KeyVal(KeyVal {
+            k: "EMPTY",
+            v: "".to_string(),
+            q: Quote::No,
+        })
+    );
+
+    assert_eq!(Line::from("\u{feff}# comment\r"), Line::Empty);
+    assert_eq!(Line::from("\r"), Line::Empty);
+    assert_eq!(
+        Line::from("#include .env.common\r"),
+        Line::Include(".env.common")
+    );
+}
@@ -0,0 +1,26 @@
+// Snapshot is global, so it is tested in its own process
+use std::{env, thread};
+use zenv::{zenv, Zenv};
+
+#[test]
+fn snapshot() {
+    assert!(zenv!(init, "tests/.env.expanded", true).is_some());
+    assert!(Zenv::new("tests/.env.basic", false).init().is_err());
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| zenv::var("EXPANDED")))
+        .collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), Ok("basic-expanded".to_string()));
+    }
+
+    // Process environment is not modified, but is used as a fallback
+    assert!(env::var("EXPANDED").is_err());
+    assert_eq!(zenv::var("PATH"), env::var("PATH"));
+
+    let vars = zenv::vars();
+
+    assert_eq!(vars["BASIC"], "basic");
+    assert_eq!(vars.get("PATH"), env::var("PATH").ok().as_ref());
+}
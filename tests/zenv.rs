@@ -1,4 +1,4 @@
-use zenv::{zenv, Zenv};
+use zenv::{zenv, Lines, Zenv};
 
 #[test]
 fn zenv_basic() {
@@ -40,6 +40,221 @@ fn zenv_expanded() {
     assert_ne!(z.get("SYSTEM_VAR").unwrap(), "_exist");
 }
 
+// Commands are run by `cmd` on windows
+#[cfg(unix)]
+#[test]
+fn zenv_commands() {
+    let z = Zenv::new("tests/.env.command", true)
+        .commands(true)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z.get("COMMAND").unwrap(), "basic_command");
+    assert_eq!(z.get("TRIMMED").unwrap(), "trimmed");
+    assert_eq!(z.get("NESTED").unwrap(), "nested");
+    assert_eq!(z.get("NOT_COMMAND").unwrap(), "$(echo not_command)");
+}
+
+#[test]
+fn zenv_commands_disabled() {
+    let z = Zenv::new("tests/.env.command", true).parse().unwrap();
+
+    assert_ne!(z.get("COMMAND").unwrap(), "basic_command");
+}
+
+#[cfg(unix)]
+#[test]
+fn zenv_commands_error() {
+    use std::{io::ErrorKind, time::Duration};
+
+    let err = Lines::from("\nFAILED=\"$(exit 3)\"")
+        .expand_commands(Duration::from_secs(5))
+        .unwrap_err();
+
+    assert!(err.to_string().starts_with("Line 2: "));
+
+    let err = Lines::from("SLOW=\"$(sleep 5)\"")
+        .expand_commands(Duration::from_millis(100))
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+#[test]
+fn zenv_files() {
+    let z = Zenv::new("tests/.env.files", false)
+        .files(true)
+        .file_keys(true)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z.get("INLINE").unwrap(), "from_file");
+    assert_eq!(z.get("QUOTED").unwrap(), "from_file");
+    assert_eq!(z.get("DB_PASSWORD").unwrap(), "from_file");
+    assert_eq!(z.get("DB_PASSWORD_FILE").unwrap(), "secrets/value");
+    assert_eq!(z.get("PLAIN").unwrap(), "file:secrets/value");
+}
+
+#[test]
+fn zenv_files_missing() {
+    use std::io::ErrorKind;
+
+    let err = Zenv::new("tests/.env.files.missing", false)
+        .files(true)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.to_string().contains("`MISSING`"));
+}
+
+#[test]
+fn zenv_files_suffix() {
+    use std::io::ErrorKind;
+
+    // `KEY_FILE` is only read if it is enabled
+    let z = Zenv::new("tests/.env.files.suffix", false)
+        .files(true)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z.get("LOG_FILE").unwrap(), "logs/app.log");
+    assert_eq!(z.get("LOG"), None);
+
+    let err = Zenv::new("tests/.env.files.suffix", false)
+        .file_keys(true)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.to_string().contains("`LOG_FILE`"));
+
+    let err = Zenv::from_str("HOST=localhost\nHOST_FILE=tests/secrets/value", false)
+        .file_keys(true)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "Both `HOST` and `HOST_FILE` are set");
+}
+
+#[test]
+fn zenv_files_disabled() {
+    let z = Zenv::new("tests/.env.files", false).parse().unwrap();
+
+    assert_eq!(z.get("INLINE").unwrap(), "@file:secrets/value");
+    assert_eq!(z.get("DB_PASSWORD"), None);
+}
+
+#[test]
+fn zenv_include() {
+    let z = Zenv::new("tests/include/.env", true)
+        .includes(true)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z.get("COMMON").unwrap(), "common");
+    assert_eq!(z.get("LOCAL").unwrap(), "local");
+    assert_eq!(z.get("OVERRIDDEN").unwrap(), "overridden");
+    assert_eq!(z.get("EXPANDED").unwrap(), "common_local");
+
+    // Includes are disabled by default, so the directives are comments
+    let z = Zenv::from_str("#include this in prod\nA=1", false)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z.get("A").unwrap(), "1");
+}
+
+#[test]
+fn zenv_include_files() {
+    // Paths are relative to the file which defines the key
+    let z = Zenv::new("tests/include/.env.files", false)
+        .files(true)
+        .file_keys(true)
+        .includes(true)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z["SUB_INLINE"], "from_sub");
+    assert_eq!(z["SUB_PASSWORD"], "from_sub");
+    assert_eq!(z["ROOT_INLINE"], "from_root");
+}
+
+#[test]
+fn zenv_include_origin() {
+    use std::path::PathBuf;
+
+    let z = Zenv::new("tests/include/.env", false).includes(true);
+
+    let local = z.origin("LOCAL").unwrap().unwrap();
+    assert_eq!(
+        local.file,
+        Some(PathBuf::from("tests/include/./.env.local"))
+    );
+    assert_eq!(local.line, 1);
+
+    let overridden = z.origin("OVERRIDDEN").unwrap().unwrap();
+    assert_eq!(overridden.file, Some(PathBuf::from("tests/include/.env")));
+    assert_eq!(overridden.line, 3);
+
+    assert_eq!(z.origin("NOT_FOUND").unwrap(), None);
+}
+
+#[test]
+fn zenv_parse_origin() {
+    use zenv::Quote;
+
+    let z = Zenv::new("tests/include/.env", true)
+        .includes(true)
+        .parse_origin()
+        .unwrap();
+
+    let (val, origin) = z.get("OVERRIDDEN").unwrap();
+    assert_eq!(val.expose(), "overridden");
+    assert_eq!(origin.raw, "OVERRIDDEN=overridden");
+    assert_eq!(origin.quote, Quote::No);
+    assert!(origin.is_override());
+    assert!(!origin.is_expanded());
+    assert_eq!(origin.overrides[0].raw, "OVERRIDDEN=common");
+
+    let (val, origin) = z.get("EXPANDED").unwrap();
+    assert_eq!(val.expose(), "common_local");
+    assert_eq!(origin.quote, Quote::Double);
+    assert_eq!(origin.refs, vec!["COMMON", "LOCAL"]);
+    assert!(!origin.is_override());
+}
+
+#[test]
+fn zenv_include_cycle() {
+    use std::io::ErrorKind;
+
+    let err = Zenv::new("tests/include/.env.cycle", false)
+        .includes(true)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("Include cycle detected"));
+}
+
+#[test]
+fn zenv_redacted() {
+    let z = Zenv::new("tests/.env.secret", false)
+        .parse_redacted()
+        .unwrap();
+
+    assert!(!z.get("PORT").unwrap().is_secret());
+    assert!(z.get("GITHUB_TOKEN").unwrap().is_secret());
+    assert!(z.get("DB_PASSWORD").unwrap().is_secret());
+    assert!(z.get("APP_SECRET").unwrap().is_secret());
+    assert!(z.get("API_KEY").unwrap().is_secret());
+
+    assert_eq!(format!("{:?}", z.get("DB_PASSWORD").unwrap()), "***");
+    assert_eq!(z.get("DB_PASSWORD").unwrap().expose(), "db_password");
+    assert_eq!(z.get("API_KEY").unwrap().expose(), "api_key");
+}
+
 #[test]
 fn zenv_macro_basic() {
     use std::env::var_os;
@@ -63,3 +278,669 @@ fn zenv_macro_expanded() {
     assert_eq!(var_os("EXPANDED").unwrap(), "basic-expanded");
     assert_eq!(var_os("DOUBLE_EXPANDED").unwrap(), "basic-basic-expanded");
 }
+
+#[test]
+fn zenv_watch() {
+    use std::{fs, sync::mpsc, time::Duration};
+    use zenv::Change;
+
+    let dir = std::env::temp_dir().join(format!("zenv-reload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(".env");
+    fs::write(&path, "KEEP=keep\nCHANGE=old\nREMOVE=remove\n").unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let watcher = Zenv::new(path.to_str().unwrap(), false)
+        .includes(true)
+        .watch(Duration::from_millis(50), move |x| {
+            tx.send(x).ok();
+        })
+        .unwrap();
+
+    let wait = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    // Malformed edits are reported and the last good variables are kept
+    fs::write(&path, "#include missing\n").unwrap();
+    assert!(wait().is_err());
+
+    fs::write(&path, "KEEP=keep\nCHANGE=new\nADD=add\n").unwrap();
+    let changes = wait().unwrap();
+
+    assert_eq!(changes.len(), 3);
+    assert!(matches!(&changes[0], Change::Changed { key, old, new }
+        if key == "CHANGE" && old.expose() == "old" && new.expose() == "new"));
+    assert!(matches!(&changes[1], Change::Added { key, val }
+        if key == "ADD" && val.expose() == "add"));
+    assert!(matches!(&changes[2], Change::Removed { key, .. } if key == "REMOVE"));
+
+    changes.iter().for_each(Change::apply);
+    assert_eq!(std::env::var("ADD").unwrap(), "add");
+
+    watcher.stop();
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn zenv_from_reader() {
+    let data = "#include tests/.env.common\nLOCAL=\"${COMMON}_local\"\n";
+
+    let zenv = Zenv::from_reader(data.as_bytes(), true)
+        .unwrap()
+        .includes(true);
+    let z = zenv.parse().unwrap();
+
+    assert_eq!(z.get("LOCAL").unwrap(), "common_local");
+    assert_eq!(
+        zenv.paths().unwrap(),
+        vec![std::path::PathBuf::from("tests/.env.common")]
+    );
+    assert!(!format!("{:?}", zenv).contains("_local"));
+
+    let origin = zenv.origin("LOCAL").unwrap().unwrap();
+    assert_eq!(origin.file, None);
+    assert_eq!(origin.line, 2);
+}
+
+#[test]
+fn zenv_from_str() {
+    let data = include_str!(".env.secret");
+
+    let z = Zenv::from_str(data, false)
+        .secrets(&["*_KEY"])
+        .parse_redacted()
+        .unwrap();
+
+    assert!(z["API_KEY"].is_secret());
+    assert_eq!(z["PORT"].expose(), "5000");
+
+    let z = Zenv::from_str("TOKEN=@file:tests/secrets/value", false)
+        .files(true)
+        .parse()
+        .unwrap();
+
+    assert_eq!(z.get("TOKEN").unwrap(), "from_file");
+}
+
+#[test]
+fn zenv_from_bytes() {
+    let z = Zenv::from_bytes(b"BASIC=basic\n", false).parse().unwrap();
+
+    assert_eq!(z.get("BASIC").unwrap(), "basic");
+
+    let err = Zenv::from_bytes(b"BASIC=basic\nVALUE=\xc3\xa9\xff", false)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().ends_with("at line 2, column 8"));
+
+    let path = std::env::temp_dir().join(format!("zenv-bytes-{}", std::process::id()));
+    std::fs::write(&path, b"\xffBASIC=basic\n").unwrap();
+
+    let err = Zenv::new(&path, false).parse().unwrap_err();
+
+    assert!(err
+        .to_string()
+        .ends_with("not valid UTF-8 - invalid byte at line 1, column 1"));
+
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn zenv_build_directives() {
+    use std::path::PathBuf;
+
+    // Separators of the joined paths differ on windows, so they are compared as paths
+    let reruns = |directives: &[String]| -> Vec<PathBuf> {
+        directives
+            .iter()
+            .filter_map(|x| x.strip_prefix("cargo:rerun-if-changed="))
+            .map(PathBuf::from)
+            .collect()
+    };
+
+    let zenv = Zenv::new("tests/include/.env", false).includes(true);
+    let directives = zenv::build::directives(&zenv).unwrap();
+
+    assert!(directives.contains(&"cargo:rustc-env=OVERRIDDEN=overridden".to_string()));
+    assert_eq!(
+        reruns(&directives),
+        [
+            PathBuf::from("tests/include/.env"),
+            PathBuf::from("tests/include/../.env.common"),
+            PathBuf::from("tests/include/./.env.local"),
+        ]
+    );
+
+    let zenv = Zenv::new("tests/.env.files", false).files(true);
+    let directives = zenv::build::directives(&zenv).unwrap();
+
+    assert_eq!(
+        reruns(&directives),
+        [
+            PathBuf::from("tests/.env.files"),
+            PathBuf::from("tests/secrets/value"),
+        ]
+    );
+
+    let zenv = Zenv::from_str(r#"MULTI="first\nsecond""#, false);
+    let err = zenv::build::directives(&zenv).unwrap_err();
+
+    assert!(err.to_string().contains("`MULTI` contains a newline"));
+}
+
+#[test]
+fn zenv_scoped() {
+    use std::{env, thread, time::Duration};
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            thread::spawn(move || {
+                let (path, expected) = match i % 2 {
+                    0 => ("tests/.env.common", "common"),
+                    _ => ("tests/include/.env", "overridden"),
+                };
+
+                let _guard = Zenv::new(path, false).scoped().unwrap();
+                thread::sleep(Duration::from_millis(20));
+
+                assert_eq!(env::var("OVERRIDDEN").unwrap(), expected);
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(env::var_os("OVERRIDDEN"), None);
+    assert_eq!(env::var_os("LOCAL"), None);
+
+    // Previous values are restored
+    env::set_var("COMMON", "before");
+
+    {
+        let _guard = Zenv::new("tests/.env.common", false).scoped().unwrap();
+        assert_eq!(env::var("COMMON").unwrap(), "common");
+    }
+
+    assert_eq!(env::var("COMMON").unwrap(), "before");
+    env::remove_var("COMMON");
+
+    // Nested guards on the same thread don't wait for the lock held by the outer one
+    {
+        let _outer = Zenv::new("tests/.env.common", false).scoped().unwrap();
+
+        {
+            let _inner = Zenv::new("tests/include/.env", false).scoped().unwrap();
+            assert_eq!(env::var("OVERRIDDEN").unwrap(), "overridden");
+        }
+
+        assert_eq!(env::var("OVERRIDDEN").unwrap(), "common");
+    }
+
+    assert_eq!(env::var_os("OVERRIDDEN"), None);
+}
+
+#[test]
+fn zenv_non_utf8() {
+    use std::{env, ffi::OsStr, fs};
+
+    let dir = env::temp_dir().join(format!("zenv-non-utf8-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(".env");
+
+    // Keys are unique to this test, as the other tests also set the environment
+    fs::write(
+        &path,
+        b"#include .env.other\nNON_UTF8_INVALID=\"in\xffvalid\"\nNON_UTF8_EXPANDED=\"${NON_UTF8_OTHER}\"\n",
+    )
+    .unwrap();
+    fs::write(dir.join(".env.other"), b"NON_UTF8_OTHER=ot\xfe\xffher\n").unwrap();
+
+    let zenv = Zenv::new(&path, true).includes(true);
+
+    assert!(zenv.parse().is_err());
+
+    // Invalid bytes are replaced
+    let lossy = zenv.clone().lossy(true).parse().unwrap();
+
+    assert_eq!(lossy["NON_UTF8_INVALID"], "in\u{FFFD}valid");
+    assert_eq!(lossy["NON_UTF8_EXPANDED"], "ot\u{FFFD}\u{FFFD}her");
+
+    // or kept as they are
+    let parsed = zenv.parse_os().unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            parsed[OsStr::new("NON_UTF8_INVALID")].as_bytes(),
+            b"in\xffvalid"
+        );
+        assert_eq!(
+            parsed[OsStr::new("NON_UTF8_EXPANDED")].as_bytes(),
+            b"ot\xfe\xffher"
+        );
+
+        zenv.configure().unwrap();
+        assert_eq!(
+            env::var_os("NON_UTF8_INVALID").unwrap().as_bytes(),
+            b"in\xffvalid"
+        );
+        env::remove_var("NON_UTF8_INVALID");
+        env::remove_var("NON_UTF8_EXPANDED");
+        env::remove_var("NON_UTF8_OTHER");
+    }
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn zenv_private_use() {
+    use std::ffi::OsStr;
+
+    // Private use chars and noncharacters are not mistaken for the invalid bytes
+    let text = "PUA=a\u{10FF41}b\nNONCHAR=\u{FDD0}\u{10FF41}\u{FDD0}\n";
+    let zenv = Zenv::from_str(text, false);
+
+    let parsed = zenv.parse().unwrap();
+    assert_eq!(parsed["PUA"], "a\u{10FF41}b");
+    assert_eq!(parsed["NONCHAR"], "\u{FDD0}\u{10FF41}\u{FDD0}");
+
+    let lossy = zenv.clone().lossy(true).parse().unwrap();
+    assert_eq!(lossy, parsed);
+
+    let parsed = zenv.parse_os().unwrap();
+    assert_eq!(parsed[OsStr::new("PUA")], "a\u{10FF41}b");
+    assert_eq!(parsed[OsStr::new("NONCHAR")], "\u{FDD0}\u{10FF41}\u{FDD0}");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut bytes = "MIXED=\u{FDD0}\u{10FF41}".as_bytes().to_vec();
+        bytes.push(0xff);
+
+        let parsed = Zenv::from_bytes(&bytes, false).parse_os().unwrap();
+        assert_eq!(parsed[OsStr::new("MIXED")].as_bytes(), &bytes[6..]);
+    }
+}
+
+#[test]
+fn zenv_bom_crlf_utf16() {
+    let text = "BASIC=basic\r\nS_QUOTE='single'\r\nD_QUOTE=\"${BASIC}\"\r\nLAST=last\r";
+
+    let expected = Zenv::from_str(
+        "BASIC=basic\nS_QUOTE='single'\nD_QUOTE=\"${BASIC}\"\nLAST=last",
+        true,
+    )
+    .parse()
+    .unwrap();
+
+    // UTF-8 with and without the BOM
+    let utf8 = [&b"\xEF\xBB\xBF"[..], text.as_bytes()].concat();
+
+    assert_eq!(Zenv::from_str(text, true).parse().unwrap(), expected);
+    assert_eq!(Zenv::from_bytes(&utf8, true).parse().unwrap(), expected);
+
+    // UTF-16 in both the byte orders
+    let units: Vec<u16> = std::iter::once(0xFEFF).chain(text.encode_utf16()).collect();
+    let le: Vec<u8> = units.iter().flat_map(|x| x.to_le_bytes()).collect();
+    let be: Vec<u8> = units.iter().flat_map(|x| x.to_be_bytes()).collect();
+
+    for bytes in [le, be] {
+        let zenv = Zenv::from_bytes(&bytes, true);
+        let err = zenv.parse().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Env data is UTF-16, which needs to be enabled"
+        );
+        assert_eq!(zenv.utf16(true).parse().unwrap(), expected);
+    }
+
+    // Unpaired surrogate
+    let invalid = Zenv::from_bytes(b"\xFF\xFE\x00\xD8", false)
+        .utf16(true)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(invalid.to_string(), "Env data is not valid UTF-16");
+}
+
+#[test]
+fn zenv_keys() {
+    use zenv::{Dialect, KeyPolicy, Keys};
+
+    let zenv = Zenv::new("tests/.env.keys", false);
+
+    let permissive = zenv.parse().unwrap();
+
+    assert_eq!(permissive.len(), 5);
+    assert_eq!(permissive["MY KEY"], "invalid");
+
+    let posix = zenv
+        .clone()
+        .keys(Keys {
+            policy: KeyPolicy::Posix,
+            ..Keys::default()
+        })
+        .parse_ordered()
+        .unwrap();
+
+    assert_eq!(posix, vec![("PORT".to_string(), "5000".to_string())]);
+
+    let normalized = zenv
+        .clone()
+        .keys(Keys {
+            policy: KeyPolicy::Posix,
+            uppercase: true,
+            underscore: true,
+            ..Keys::default()
+        })
+        .parse_ordered()
+        .unwrap();
+
+    let keys: Vec<&str> = normalized.iter().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, ["PORT", "APP_NAME", "MY_KEY"]);
+
+    let err = zenv
+        .clone()
+        .keys(Keys {
+            policy: KeyPolicy::Error,
+            underscore: true,
+            ..Keys::default()
+        })
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "tests/.env.keys:4:5: Invalid key `MY KEY`, expected [A-Za-z_][A-Za-z0-9_]*"
+    );
+
+    let err = Lines::parse(
+        "1ST=invalid",
+        Keys {
+            policy: KeyPolicy::Error,
+            ..Keys::default()
+        },
+    )
+    .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .starts_with("Line 1, column 1: Invalid key `1ST`"));
+
+    // Column is where the key starts in the file, for every dialect
+    let column = |dialect, text| {
+        Zenv::from_str(text, false)
+            .dialect(dialect)
+            .keys(Keys {
+                policy: KeyPolicy::Error,
+                ..Keys::default()
+            })
+            .parse()
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert!(column(Dialect::Zenv, "\u{feff}  1ST=x").starts_with("Line 1, column 3:"));
+    assert!(column(Dialect::Node, "A=1\n  export 1ST=x").starts_with("Line 2, column 10:"));
+    assert!(column(Dialect::Bash, "export A=1 1ST=2").starts_with("Line 1, column 12:"));
+    assert!(column(Dialect::Docker, "\t1ST=x").starts_with("Line 1, column 2:"));
+}
+
+#[test]
+fn zenv_duplicates() {
+    use zenv::Duplicates;
+
+    let zenv = Zenv::new("tests/.env.duplicates", true);
+
+    let last = zenv.clone().parse().unwrap();
+
+    assert_eq!(last["PORT"], "8080");
+    assert_eq!(last["URL"], "localhost:8080");
+
+    let first = zenv.clone().duplicates(Duplicates::FirstWins);
+    let parsed = first.parse().unwrap();
+
+    assert_eq!(parsed["PORT"], "5000");
+    assert_eq!(parsed["URL"], "localhost:5000");
+    assert!(!first.origin("PORT").unwrap().unwrap().is_override());
+
+    let err = zenv.duplicates(Duplicates::Error).parse().unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "tests/.env.duplicates:4: Duplicate key `PORT`, first defined at line 1"
+    );
+
+    // Overriding the included variables is not a duplicate
+    let layered = Zenv::new("tests/include/.env", false)
+        .duplicates(Duplicates::Error)
+        .parse()
+        .unwrap();
+
+    assert_eq!(layered["OVERRIDDEN"], "overridden");
+}
+
+#[test]
+fn zenv_filter() {
+    let zenv = Zenv::new("tests/.env.filter", true).prefix("API_");
+
+    let parsed = zenv.clone().parse().unwrap();
+
+    assert_eq!(parsed.len(), 3);
+    assert_eq!(parsed["API_URL"], "localhost:5000");
+
+    let stripped = zenv.strip_prefix(true).exclude(&["*_TOKEN"]);
+
+    assert_eq!(
+        stripped.parse_ordered().unwrap(),
+        vec![
+            ("PORT".to_string(), "5000".to_string()),
+            ("URL".to_string(), "localhost:5000".to_string()),
+        ]
+    );
+
+    let origin = stripped.origin("URL").unwrap().unwrap();
+
+    assert_eq!(origin.line, 3);
+    assert_eq!(origin.refs, vec!["HOST", "API_PORT"]);
+
+    let public = Zenv::new("tests/.env.filter", false)
+        .only(&["PUBLIC_URL", "HOST"])
+        .exclude(&["HOST"])
+        .parse()
+        .unwrap();
+
+    assert_eq!(public.len(), 1);
+    assert_eq!(public["PUBLIC_URL"], "localhost");
+}
+
+#[test]
+fn zenv_bash() {
+    use std::process::Command;
+    use zenv::Dialect;
+
+    let zenv = Zenv::new("tests/.env.bash", true).dialect(Dialect::Bash);
+    let parsed = zenv.parse().unwrap();
+
+    assert_eq!(parsed["BASIC"], "basic");
+    assert_eq!(parsed["URL"], "localhost#main");
+    assert_eq!(parsed["ADJACENT"], "foobarbaz");
+    assert_eq!(parsed["SINGLE"], r"no\nescape $BASIC");
+    assert_eq!(parsed["ANSI"], "tab\tnewline\n");
+    assert_eq!(parsed["ESCAPED"], "a b$BASIC");
+    assert_eq!(parsed["EXPANDED"], "basic_basic_$BASIC");
+    assert_eq!(parsed["MULTI"], "line one\nline two");
+    assert_eq!(parsed["CONTINUED"], "onetwo");
+    assert_eq!(parsed["HOST"], "localhost");
+    assert_eq!(parsed["ORDERED"], "5000");
+    assert_eq!(parsed["PORT"], "8080");
+    assert_eq!(parsed["JOINED"], "basicbasic");
+    assert_eq!(parsed["PATH_LIKE"], "basic:/x");
+    assert_eq!(parsed["SPACED"], "basic basic");
+    assert_eq!(parsed["SLASH"], "basic/x");
+    assert_eq!(parsed["POSITIONAL"], "2");
+
+    let origin = zenv.origin("MULTI").unwrap().unwrap();
+
+    assert_eq!(origin.line, 10);
+    assert_eq!(origin.raw, "MULTI=\"line one\nline two\"");
+
+    // References are kept as they are written, if the expansion is disabled
+    let literal = Zenv::from_str("A=$HOME\nB=\"x$HOME\"\nC=${HOME}$(echo)", false)
+        .dialect(Dialect::Bash)
+        .parse()
+        .unwrap();
+
+    assert_eq!(literal["A"], "$HOME");
+    assert_eq!(literal["B"], "x$HOME");
+    assert_eq!(literal["C"], "${HOME}$(echo)");
+
+    // Same values as the shell, if there is one
+    if let Ok(out) = Command::new("bash")
+        .args(["-c", "source tests/.env.bash && env -0"])
+        .env_clear()
+        .output()
+    {
+        let env = format!("\0{}", String::from_utf8(out.stdout).unwrap());
+
+        for (k, v) in &parsed {
+            assert!(env.contains(&format!("\0{}={}\0", k, v)), "{} differs", k);
+        }
+    }
+
+    let err = Zenv::from_str("A=1\nB=\"unclosed\nC=3", false)
+        .dialect(Dialect::Bash)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(err.to_string(), "Line 2: Unclosed `\"`");
+
+    let err = Zenv::from_str("A=1 echo", false)
+        .dialect(Dialect::Bash)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Line 1: Unsupported command `echo`, expected KEY=VALUE, export, set or source"
+    );
+}
+
+#[test]
+fn zenv_dialects() {
+    use zenv::Dialect;
+
+    let parse = |dialect: Dialect| {
+        Zenv::new("tests/.env.dialects", true)
+            .dialect(dialect)
+            .parse()
+            .unwrap()
+    };
+
+    let node = parse(Dialect::Node);
+
+    assert_eq!(node["URL"], "localhost");
+    assert_eq!(node["NEWLINE"], "a\nb");
+    assert_eq!(node["SINGLE"], r"a\nb");
+    assert_eq!(node["EXPANDED"], "${BASIC}_$BASIC");
+    assert_eq!(node["MULTI"], "line one\nline two");
+
+    let python = parse(Dialect::Python);
+
+    assert_eq!(python["URL"], "localhost#main");
+    assert_eq!(python["COMMENT"], "value");
+    assert_eq!(python["EXPANDED"], "basic_$BASIC");
+    assert_eq!(python["DEFAULT"], "fallback");
+    assert_eq!(python["ESCAPED"], r"\$BASIC");
+
+    let ruby = parse(Dialect::Ruby);
+
+    assert_eq!(ruby["URL"], "localhost");
+    assert_eq!(ruby["EXPANDED"], "basic_basic");
+    assert_eq!(ruby["DEFAULT"], ":-fallback}");
+    assert_eq!(ruby["ESCAPED"], "$BASIC");
+
+    let ruby = Zenv::new("tests/.env.dialects", false)
+        .dialect(Dialect::Ruby)
+        .parse()
+        .unwrap();
+
+    assert_eq!(ruby["EXPANDED"], "${BASIC}_$BASIC");
+    assert_eq!(ruby["DEFAULT"], "${MISSING:-fallback}");
+
+    let compose = parse(Dialect::Compose);
+
+    assert_eq!(compose["URL"], "localhost#main");
+    assert_eq!(compose["COMMENT"], "value");
+    assert_eq!(compose["SINGLE"], r"a\nb");
+    assert_eq!(compose["DEFAULT"], "fallback");
+    assert_eq!(compose["MULTI"], "line one\nline two");
+
+    let origin = Zenv::new("tests/.env.dialects", false)
+        .dialect(Dialect::Compose)
+        .origin("MULTI")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(origin.line, 9);
+    assert_eq!(origin.raw, "MULTI=\"line one\nline two\"");
+
+    let err = Zenv::from_str("A=1\nB=\"unclosed", false)
+        .dialect(Dialect::Compose)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Line 2: Unclosed `\"`, or text after the closing quote, in `B`"
+    );
+
+    // Python skips the statement, while node reads it as unquoted
+    let zenv = Zenv::from_str("A='a'b\nB=b", false);
+
+    let python = zenv.clone().dialect(Dialect::Python).parse().unwrap();
+    assert_eq!(python.get("A"), None);
+    assert_eq!(python["B"], "b");
+
+    let node = zenv.dialect(Dialect::Node).parse().unwrap();
+    assert_eq!(node["A"], "'a'b");
+}
+
+#[test]
+fn zenv_docker() {
+    use std::env;
+    use zenv::Dialect;
+
+    let parsed = Zenv::new("tests/.env.docker", true)
+        .dialect(Dialect::Docker)
+        .parse()
+        .unwrap();
+
+    assert_eq!(parsed["BASIC"], "basic");
+    assert_eq!(parsed["QUOTED"], "\"quoted\"");
+    assert_eq!(parsed["COMMENT"], "value # not a comment");
+    assert_eq!(parsed["INDENTED"], "indented");
+    assert_eq!(parsed["EXPANDED"], "$BASIC");
+    assert_eq!(parsed["EQUALS"], "a=b");
+    assert_eq!(parsed.get("HOME"), env::var("HOME").ok().as_ref());
+    assert_eq!(parsed.get("ZENV_DOCKER_UNSET"), None);
+
+    let err = Zenv::from_str("A=1\nMY KEY=2", false)
+        .dialect(Dialect::Docker)
+        .parse()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Line 2: Variable `MY KEY` contains whitespaces"
+    );
+}